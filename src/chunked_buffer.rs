@@ -0,0 +1,123 @@
+/// A logical byte stream backed by a queue of chunks rather than one
+/// contiguous allocation: `push` appends a new chunk on the right without
+/// touching what's already buffered, and `drain_up_to` consumes bytes from
+/// the left without ever copying more than what's returned. A framer can
+/// grow a `ChunkedBuffer` incrementally as reads arrive, and a consumer
+/// (bulk-string or RDB parsing, via the `Read` impl below) can drain it a
+/// bounded chunk at a time instead of requiring the whole payload as one
+/// contiguous slice - keeping peak memory proportional to the chunk size
+/// rather than to the full value.
+use std::collections::VecDeque;
+use bytes::{Buf, Bytes};
+
+#[derive(Default)]
+pub struct ChunkedBuffer {
+    chunks: VecDeque<Bytes>,
+    len: usize,
+}
+
+impl ChunkedBuffer {
+    pub fn new() -> ChunkedBuffer {
+        ChunkedBuffer { chunks: VecDeque::new(), len: 0 }
+    }
+
+    pub fn push(&mut self, chunk: Bytes) {
+        if !chunk.is_empty() {
+            self.len += chunk.len();
+            self.chunks.push_back(chunk);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes and returns up to `max_len` bytes from the front of the
+    /// buffer, copying only what's returned - never the chunks behind it.
+    pub fn drain_up_to(&mut self, max_len: usize) -> Bytes {
+        if max_len == 0 || self.chunks.is_empty() {
+            return Bytes::new();
+        }
+        let front_len = self.chunks[0].len();
+        if front_len <= max_len {
+            let chunk = self.chunks.pop_front().unwrap();
+            self.len -= chunk.len();
+            chunk
+        } else {
+            let taken = self.chunks[0].slice(0..max_len);
+            self.chunks[0].advance(max_len);
+            self.len -= max_len;
+            taken
+        }
+    }
+}
+
+impl std::io::Read for ChunkedBuffer {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let chunk = self.drain_up_to(buf.len());
+        buf[0..chunk.len()].copy_from_slice(&chunk);
+        Ok(chunk.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn should_report_the_combined_length_of_every_pushed_chunk() {
+        let mut buffer = ChunkedBuffer::new();
+        assert!(buffer.is_empty());
+
+        buffer.push(Bytes::from_static(b"hel"));
+        buffer.push(Bytes::from_static(b"lo"));
+
+        assert_eq!(buffer.len(), 5);
+        assert!(!buffer.is_empty());
+    }
+
+    #[test]
+    fn should_drain_within_a_single_chunk_without_consuming_the_next_one() {
+        let mut buffer = ChunkedBuffer::new();
+        buffer.push(Bytes::from_static(b"hello"));
+        buffer.push(Bytes::from_static(b"world"));
+
+        assert_eq!(buffer.drain_up_to(2), Bytes::from_static(b"he"));
+        assert_eq!(buffer.drain_up_to(3), Bytes::from_static(b"llo"));
+        assert_eq!(buffer.len(), 5);
+        assert_eq!(buffer.drain_up_to(100), Bytes::from_static(b"world"));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn should_drain_across_a_chunk_boundary_one_chunk_at_a_time() {
+        let mut buffer = ChunkedBuffer::new();
+        buffer.push(Bytes::from_static(b"ab"));
+        buffer.push(Bytes::from_static(b"cde"));
+
+        // A single `drain_up_to` call never spans more than the chunk
+        // currently at the front, even if more bytes are available overall.
+        assert_eq!(buffer.drain_up_to(4), Bytes::from_static(b"ab"));
+        assert_eq!(buffer.drain_up_to(4), Bytes::from_static(b"cde"));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn should_implement_read_by_draining_bounded_chunks() {
+        let mut buffer = ChunkedBuffer::new();
+        buffer.push(Bytes::from_static(b"abcdef"));
+
+        let mut out = [0u8; 4];
+        assert_eq!(buffer.read(&mut out).unwrap(), 4);
+        assert_eq!(&out, b"abcd");
+
+        let mut rest = Vec::new();
+        buffer.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"ef");
+    }
+}
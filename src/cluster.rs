@@ -0,0 +1,312 @@
+/// Full-mesh cluster membership and peer gossip.
+///
+/// Unlike `ServerState`'s single `replica_of` relationship, a `PeerTable`
+/// lets a node track an arbitrary number of peers by node id. Nodes
+/// periodically dial each known peer, exchange peer lists over `CLUSTER
+/// GOSSIP`, and learn about (and then themselves dial) any peer they didn't
+/// already know about, so a mesh seeded from a single address converges to
+/// fully connected without any node needing the full membership up front.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, ensure};
+
+use crate::io;
+use crate::protocol;
+use crate::secure_transport;
+use crate::server_state::ServerState;
+
+/// How often the gossip loop dials every peer that's currently due for a retry.
+pub const GOSSIP_TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Consecutive failed gossip attempts after which a peer is marked down.
+const MAX_MISSED_PINGS: u32 = 3;
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// What the mesh knows about one peer.
+struct PeerState {
+    address: String,
+    consecutive_misses: u32,
+    up: bool,
+    backoff: Duration,
+    next_attempt_at: Instant,
+}
+
+impl PeerState {
+    fn newly_learned(address: &str) -> PeerState {
+        PeerState {
+            address: address.to_owned(),
+            consecutive_misses: 0,
+            up: true,
+            backoff: INITIAL_BACKOFF,
+            next_attempt_at: Instant::now(),
+        }
+    }
+}
+
+/// The set of peers a node knows about, keyed by node id, plus this node's
+/// own id and address so they can be included when gossiping.
+pub struct PeerTable {
+    self_node_id: String,
+    self_address: String,
+    peers: HashMap<String, PeerState>,
+}
+
+impl PeerTable {
+    pub fn new(self_node_id: String, self_address: String) -> PeerTable {
+        PeerTable {
+            self_node_id,
+            self_address,
+            peers: HashMap::new(),
+        }
+    }
+
+    /// Node ids of every known peer other than this node itself.
+    pub fn peer_ids(&self) -> Vec<String> {
+        self.peers.keys().cloned().collect()
+    }
+
+    /// Node ids that are due for a gossip attempt, i.e. aren't backing off.
+    pub fn due_peer_ids(&self, now: Instant) -> Vec<(String, String)> {
+        self.peers
+            .iter()
+            .filter(|(_, peer)| now >= peer.next_attempt_at)
+            .map(|(id, peer)| (id.clone(), peer.address.clone()))
+            .collect()
+    }
+
+    pub fn is_up(&self, node_id: &str) -> bool {
+        self.peers.get(node_id).map(|peer| peer.up).unwrap_or(false)
+    }
+
+    /// Records a peer learned either from a seed address or from another
+    /// peer's gossip. Returns `false` for this node's own id or a peer
+    /// already known, so callers can tell which peers are newly discovered.
+    pub fn learn(&mut self, node_id: &str, address: &str) -> bool {
+        if node_id == self.self_node_id || self.peers.contains_key(node_id) {
+            return false;
+        }
+        self.peers.insert(node_id.to_owned(), PeerState::newly_learned(address));
+        true
+    }
+
+    /// Merges a gossiped `(node_id, address)` list, returning the subset
+    /// that was newly learned so the caller can dial them right away.
+    pub fn merge_gossip(&mut self, entries: &[(String, String)]) -> Vec<(String, String)> {
+        entries.iter().filter(|(id, address)| self.learn(id, address)).cloned().collect()
+    }
+
+    /// This node's view of the mesh: every known peer plus itself, suitable
+    /// for sending as a `CLUSTER GOSSIP` payload.
+    pub fn gossip_entries(&self) -> Vec<(String, String)> {
+        let mut entries: Vec<(String, String)> = self
+            .peers
+            .iter()
+            .map(|(id, peer)| (id.clone(), peer.address.clone()))
+            .collect();
+        entries.push((self.self_node_id.clone(), self.self_address.clone()));
+        entries
+    }
+
+    /// Records a successful gossip round-trip with `node_id`: resets its
+    /// miss count and backoff and marks it up.
+    pub fn record_success(&mut self, node_id: &str) {
+        if let Some(peer) = self.peers.get_mut(node_id) {
+            peer.consecutive_misses = 0;
+            peer.up = true;
+            peer.backoff = INITIAL_BACKOFF;
+            peer.next_attempt_at = Instant::now();
+        }
+    }
+
+    /// Records a failed gossip attempt with `node_id`: doubles its backoff
+    /// (up to `MAX_BACKOFF`) and marks it down once `MAX_MISSED_PINGS`
+    /// consecutive attempts have failed.
+    pub fn record_failure(&mut self, node_id: &str) {
+        if let Some(peer) = self.peers.get_mut(node_id) {
+            peer.consecutive_misses += 1;
+            if peer.consecutive_misses >= MAX_MISSED_PINGS {
+                peer.up = false;
+            }
+            peer.backoff = (peer.backoff * 2).min(MAX_BACKOFF);
+            peer.next_attempt_at = Instant::now() + peer.backoff;
+        }
+    }
+}
+
+/// Flattens `(node_id, address)` pairs into the `[id, address, id, address,
+/// ...]` bulk string array `CLUSTER GOSSIP` sends and replies with.
+pub fn serialize_peer_entries(entries: &[(String, String)]) -> protocol::DataType {
+    let elements = entries
+        .iter()
+        .flat_map(|(id, address)| vec![protocol::bulk_string(id), protocol::bulk_string(address)])
+        .collect();
+    protocol::array(elements)
+}
+
+/// Inverse of `serialize_peer_entries`.
+pub fn parse_peer_entries(message: &protocol::DataType) -> Result<Vec<(String, String)>, anyhow::Error> {
+    let flat = message.as_vec()?;
+    ensure!(flat.len() % 2 == 0, "Peer list must have an even number of elements, got {:?}", flat);
+    Ok(flat.chunks(2).map(|pair| (pair[0].clone(), pair[1].clone())).collect())
+}
+
+/// Dials `address`, PINGs it to confirm liveness, then exchanges peer lists
+/// over `CLUSTER GOSSIP`, merging the reply into `server_state`'s
+/// `PeerTable`. Returns the peers newly learned from that exchange.
+fn gossip_with_address(server_state: &Arc<ServerState>, address: &str) -> Result<Vec<(String, String)>, anyhow::Error> {
+    let stream = TcpStream::connect(address)?;
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+
+    let (secure, network_key) = {
+        let config = server_state.config.lock().map_err(|e| anyhow!("Failed to lock config: {}", e))?;
+        (config.secure == "yes", config.network_key.clone())
+    };
+    let (mut reader, writer) = secure_transport::negotiate_client(stream, secure, network_key.as_bytes(), &server_state.node_identity)?;
+
+    let ping = protocol::array(vec![protocol::bulk_string("PING")]);
+    writer.lock().map_err(|e| anyhow!("Failed to lock gossip writer: {}", e))?.write_all(&ping.serialize())?;
+    match io::read_single_message(&mut reader)? {
+        Some(pong) => ensure!(pong.as_string()? == "PONG", "Expected PONG from peer {}", address),
+        None => return Err(anyhow!("Peer {} closed the connection before replying to PING", address)),
+    }
+
+    let own_entries = server_state.peer_table.lock().map_err(|e| anyhow!("Failed to lock peer table: {}", e))?.gossip_entries();
+    let gossip = protocol::array(vec![
+        protocol::bulk_string("CLUSTER"),
+        protocol::bulk_string("GOSSIP"),
+        serialize_peer_entries(&own_entries),
+    ]);
+    writer.lock().map_err(|e| anyhow!("Failed to lock gossip writer: {}", e))?.write_all(&gossip.serialize())?;
+
+    let reply = io::read_single_message(&mut reader)?
+        .ok_or_else(|| anyhow!("Peer {} closed the connection before replying to CLUSTER GOSSIP", address))?;
+    let reply_entries = parse_peer_entries(&reply)?;
+
+    let mut peer_table = server_state.peer_table.lock().map_err(|e| anyhow!("Failed to lock peer table: {}", e))?;
+    Ok(peer_table.merge_gossip(&reply_entries))
+}
+
+/// Background gossip loop, spawned alongside `join_cluster` in `main`. Dials
+/// `seed_address` once to bootstrap the mesh (if given), then repeatedly
+/// re-dials every known peer that's due for a retry, learning about (and
+/// implicitly scheduling a dial to) any peer it doesn't already know.
+pub fn run_gossip_loop(server_state: Arc<ServerState>, seed_address: Option<String>) {
+    if let Some(seed_address) = seed_address {
+        match gossip_with_address(&server_state, &seed_address) {
+            Ok(learned) => {
+                for (node_id, address) in learned {
+                    println!("Learned about cluster peer {} at {} from seed {}", node_id, address, seed_address);
+                }
+            }
+            Err(error) => println!("Failed to gossip with seed {}: {}", seed_address, error),
+        }
+    }
+
+    loop {
+        let due = {
+            let peer_table = server_state.peer_table.lock().unwrap();
+            peer_table.due_peer_ids(Instant::now())
+        };
+        for (node_id, address) in due {
+            match gossip_with_address(&server_state, &address) {
+                Ok(learned) => {
+                    server_state.peer_table.lock().unwrap().record_success(&node_id);
+                    for (learned_id, learned_address) in learned {
+                        println!("Learned about cluster peer {} at {} via {}", learned_id, learned_address, address);
+                    }
+                }
+                Err(error) => {
+                    println!("Gossip with peer {} at {} failed: {}", node_id, address, error);
+                    server_state.peer_table.lock().unwrap().record_failure(&node_id);
+                }
+            }
+        }
+        thread::sleep(GOSSIP_TICK_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_not_learn_self_or_already_known_peers() {
+        let mut table = PeerTable::new("self-id".to_owned(), "127.0.0.1:6379".to_owned());
+        assert!(!table.learn("self-id", "127.0.0.1:6379"));
+        assert!(table.learn("peer-a", "127.0.0.1:6380"));
+        assert!(!table.learn("peer-a", "127.0.0.1:9999"));
+        assert_eq!(table.peer_ids(), vec!["peer-a".to_owned()]);
+    }
+
+    #[test]
+    fn should_report_only_newly_learned_peers_from_gossip() {
+        let mut table = PeerTable::new("self-id".to_owned(), "127.0.0.1:6379".to_owned());
+        table.learn("peer-a", "127.0.0.1:6380");
+
+        let learned = table.merge_gossip(&[
+            ("peer-a".to_owned(), "127.0.0.1:6380".to_owned()),
+            ("peer-b".to_owned(), "127.0.0.1:6381".to_owned()),
+            ("self-id".to_owned(), "127.0.0.1:6379".to_owned()),
+        ]);
+
+        assert_eq!(learned, vec![("peer-b".to_owned(), "127.0.0.1:6381".to_owned())]);
+        let mut ids = table.peer_ids();
+        ids.sort();
+        assert_eq!(ids, vec!["peer-a".to_owned(), "peer-b".to_owned()]);
+    }
+
+    #[test]
+    fn should_include_self_in_gossip_entries() {
+        let mut table = PeerTable::new("self-id".to_owned(), "127.0.0.1:6379".to_owned());
+        table.learn("peer-a", "127.0.0.1:6380");
+
+        let mut entries = table.gossip_entries();
+        entries.sort();
+        assert_eq!(entries, vec![
+            ("peer-a".to_owned(), "127.0.0.1:6380".to_owned()),
+            ("self-id".to_owned(), "127.0.0.1:6379".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn should_mark_peer_down_after_enough_consecutive_misses() {
+        let mut table = PeerTable::new("self-id".to_owned(), "127.0.0.1:6379".to_owned());
+        table.learn("peer-a", "127.0.0.1:6380");
+        assert!(table.is_up("peer-a"));
+
+        for _ in 0..MAX_MISSED_PINGS {
+            table.record_failure("peer-a");
+        }
+        assert!(!table.is_up("peer-a"));
+
+        table.record_success("peer-a");
+        assert!(table.is_up("peer-a"));
+    }
+
+    #[test]
+    fn should_not_retry_a_peer_before_its_backoff_elapses() {
+        let mut table = PeerTable::new("self-id".to_owned(), "127.0.0.1:6379".to_owned());
+        table.learn("peer-a", "127.0.0.1:6380");
+        table.record_failure("peer-a");
+
+        assert!(table.due_peer_ids(Instant::now()).is_empty());
+        assert_eq!(table.due_peer_ids(Instant::now() + INITIAL_BACKOFF * 2).len(), 1);
+    }
+
+    #[test]
+    fn should_round_trip_peer_entries_through_the_wire_format() {
+        let entries = vec![
+            ("node-1".to_owned(), "127.0.0.1:6379".to_owned()),
+            ("node-2".to_owned(), "127.0.0.1:6380".to_owned()),
+        ];
+        let message = serialize_peer_entries(&entries);
+        assert_eq!(parse_peer_entries(&message).unwrap(), entries);
+    }
+}
@@ -0,0 +1,92 @@
+/// A `tokio_util::codec::Decoder`/`Encoder` pair for `DataType`, so a
+/// connection can be driven as `Framed<TcpStream, RespCodec>` - a
+/// `Stream`/`Sink` of RESP values - instead of hand-rolling a read-then-parse
+/// loop and scattering `write_all` calls through the dispatch code.
+///
+/// `decode` shares `DataType::try_parse` with the rest of the protocol
+/// layer, so framing decisions (when a frame is complete, how a length
+/// prefix is read) live in exactly one place regardless of which connection
+/// path - blocking (`io`), manual-async (`async_runtime`), or codec-based -
+/// is driving the socket.
+use bytes::{Buf, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+use crate::protocol::{DataType, DecodeOutcome};
+
+#[derive(Default)]
+pub struct RespCodec;
+
+impl Decoder for RespCodec {
+    type Item = DataType;
+    type Error = anyhow::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<DataType>, anyhow::Error> {
+        match DataType::try_parse(&src.to_vec(), 0)? {
+            DecodeOutcome::Complete((message, consumed)) => {
+                src.advance(consumed);
+                Ok(Some(message))
+            }
+            // Tell the `Framed` wrapper to wait for more bytes before
+            // calling `decode` again, exactly like `DecodeOutcome::Incomplete`
+            // means everywhere else in the protocol layer.
+            DecodeOutcome::Incomplete { .. } => Ok(None),
+        }
+    }
+}
+
+impl Encoder<DataType> for RespCodec {
+    type Error = anyhow::Error;
+
+    fn encode(&mut self, item: DataType, dst: &mut BytesMut) -> Result<(), anyhow::Error> {
+        dst.extend_from_slice(&item.serialize());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol;
+
+    #[test]
+    fn should_return_none_until_a_full_frame_has_arrived() {
+        let ping = protocol::array(vec![protocol::bulk_string("PING")]).serialize();
+        let mut codec = RespCodec;
+        let mut src = BytesMut::from(&ping[0..ping.len() - 1]);
+
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+
+        src.extend_from_slice(&ping[ping.len() - 1..]);
+        let message = codec.decode(&mut src).unwrap().unwrap();
+        assert_eq!(message.as_vec().unwrap(), vec!["PING"]);
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn should_leave_a_trailing_partial_frame_for_the_next_decode_call() {
+        let ping = protocol::array(vec![protocol::bulk_string("PING")]).serialize();
+        let get = protocol::array(vec![protocol::bulk_string("GET"), protocol::bulk_string("key")]).serialize();
+        let mut codec = RespCodec;
+        let mut src = BytesMut::new();
+        src.extend_from_slice(&ping);
+        src.extend_from_slice(&get[0..get.len() / 2]);
+
+        let first = codec.decode(&mut src).unwrap().unwrap();
+        assert_eq!(first.as_vec().unwrap(), vec!["PING"]);
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+
+        src.extend_from_slice(&get[get.len() / 2..]);
+        let second = codec.decode(&mut src).unwrap().unwrap();
+        assert_eq!(second.as_vec().unwrap(), vec!["GET", "key"]);
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn should_encode_a_data_type_to_its_resp_wire_format() {
+        let mut codec = RespCodec;
+        let mut dst = BytesMut::new();
+
+        codec.encode(protocol::bulk_string("OK"), &mut dst).unwrap();
+
+        assert_eq!(&dst[..], protocol::bulk_string("OK").serialize().as_slice());
+    }
+}
@@ -0,0 +1,88 @@
+/// BGREWRITEAOF command - compacts the append-only file.
+///
+/// Syntax: BGREWRITEAOF
+/// Rewrites the AOF log to one SET per key holding a string value, dropping
+/// stale overwrites accumulated by earlier writes. Keys holding a list, set,
+/// hash or sorted set are not carried into the rewritten log.
+/// Replies with an error if AOF persistence isn't enabled on this server.
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+use anyhow::anyhow;
+use crate::protocol;
+use crate::storage;
+use crate::server_state;
+use super::{CommandFactory, DispatchContext, RedisCommand};
+
+/// BGREWRITEAOF command implementation.
+pub struct BgRewriteAof<'a> {
+    pub message: &'a protocol::DataType,
+    pub server_state: &'a server_state::ServerState,
+}
+
+/// Builds `BgRewriteAof` commands for the registry.
+pub struct BgRewriteAofFactory;
+
+impl CommandFactory for BgRewriteAofFactory {
+    fn create<'a>(&self, message: &'a protocol::DataType, context: &DispatchContext<'a>) -> Box<dyn RedisCommand + 'a> {
+        Box::new(BgRewriteAof { message, server_state: context.server_state })
+    }
+}
+
+#[async_trait]
+impl RedisCommand for BgRewriteAof<'_> {
+    async fn execute(&self, storage: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
+        let storage = storage.lock().await;
+        if !self.server_state.rewrite_aof(&storage)? {
+            return Err(anyhow!("AOF is not enabled, start the server with --appendonly yes"));
+        }
+        Ok(vec![protocol::simple_string("Background append only file rewriting started")])
+    }
+
+    fn name(&self) -> &'static str {
+        "BGREWRITEAOF"
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        self.message.serialize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_bgrewriteaof_returns_error_when_aof_disabled() {
+        let server_state = server_state::ServerState::new(None, 6379);
+        let message = protocol::array(vec![protocol::bulk_string("BGREWRITEAOF")]);
+        let cmd = BgRewriteAof { message: &message, server_state: &server_state };
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        let error = cmd.execute(&storage).await.unwrap_err();
+
+        assert!(error.to_string().contains("not enabled"));
+    }
+
+    #[tokio::test]
+    async fn test_bgrewriteaof_rewrites_when_aof_enabled() {
+        use crate::aof::{AofWriter, FsyncPolicy};
+
+        let server_state = server_state::ServerState::new(None, 6379);
+        let path = std::env::temp_dir().join("redis_bgrewriteaof_test.aof");
+        let _ = std::fs::remove_file(&path);
+        server_state.enable_aof(AofWriter::open(&path, FsyncPolicy::Always).unwrap()).unwrap();
+
+        let message = protocol::array(vec![protocol::bulk_string("BGREWRITEAOF")]);
+        let cmd = BgRewriteAof { message: &message, server_state: &server_state };
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        let result = cmd.execute(&storage).await.unwrap();
+
+        assert_eq!(result[0].as_string().unwrap(), "Background append only file rewriting started");
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
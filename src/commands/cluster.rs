@@ -0,0 +1,115 @@
+/// CLUSTER command - cluster peer membership and gossip.
+///
+/// Syntax: CLUSTER GOSSIP <peer-list>
+/// `<peer-list>` is a flat `[node_id, address, node_id, address, ...]` array
+/// as produced by `cluster::serialize_peer_entries`. The sender's peer list
+/// is merged into this node's `PeerTable`, and the reply is this node's own
+/// peer list, so a single round trip exchanges both sides' membership view.
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+use anyhow::anyhow;
+use crate::cluster;
+use crate::protocol;
+use crate::storage;
+use crate::server_state;
+use super::{CommandFactory, DispatchContext, RedisCommand};
+
+/// CLUSTER command implementation.
+pub struct Cluster<'a> {
+    pub message: &'a protocol::DataType,
+    pub server_state: &'a server_state::ServerState,
+}
+
+/// Builds `Cluster` commands for the registry.
+pub struct ClusterFactory;
+
+impl CommandFactory for ClusterFactory {
+    fn create<'a>(&self, message: &'a protocol::DataType, context: &DispatchContext<'a>) -> Box<dyn RedisCommand + 'a> {
+        Box::new(Cluster { message, server_state: context.server_state })
+    }
+}
+
+#[async_trait]
+impl RedisCommand for Cluster<'_> {
+    async fn execute(&self, _: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
+        let elements = match self.message {
+            protocol::DataType::Array { elements } => elements,
+            _ => return Err(anyhow!("CLUSTER command must be sent as an array")),
+        };
+        let sub_command = elements
+            .get(1)
+            .ok_or_else(|| anyhow!("CLUSTER requires a subcommand"))?
+            .as_string()?
+            .to_uppercase();
+
+        let reply = if sub_command == "GOSSIP" {
+            let peer_list = elements.get(2).ok_or_else(|| anyhow!("CLUSTER GOSSIP requires a peer list"))?;
+            let entries = cluster::parse_peer_entries(peer_list)?;
+
+            let mut peer_table = self.server_state.peer_table.lock().map_err(|e| anyhow!("Failed to lock peer table: {}", e))?;
+            peer_table.merge_gossip(&entries);
+            cluster::serialize_peer_entries(&peer_table.gossip_entries())
+        } else {
+            return Err(anyhow!("Unsupported CLUSTER subcommand '{}'", sub_command));
+        };
+
+        Ok(vec![reply])
+    }
+
+    fn name(&self) -> &'static str {
+        "CLUSTER"
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        self.message.serialize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn storage() -> Arc<Mutex<storage::Storage>> {
+        Arc::new(Mutex::new(storage::Storage::new(HashMap::new())))
+    }
+
+    #[tokio::test]
+    async fn test_cluster_gossip_merges_sender_peers_and_replies_with_own_list() {
+        let server_state = server_state::ServerState::new(None, 6379);
+        let sent_entries = vec![
+            ("peer-a".to_owned(), "127.0.0.1:6380".to_owned()),
+            ("peer-b".to_owned(), "127.0.0.1:6381".to_owned()),
+        ];
+        let message = protocol::array(vec![
+            protocol::bulk_string("CLUSTER"),
+            protocol::bulk_string("GOSSIP"),
+            cluster::serialize_peer_entries(&sent_entries),
+        ]);
+        let cmd = Cluster { message: &message, server_state: &server_state };
+
+        let result = cmd.execute(&storage()).await.unwrap();
+
+        assert_eq!(result.len(), 1);
+        let reply_entries = cluster::parse_peer_entries(&result[0]).unwrap();
+        assert!(reply_entries.contains(&(server_state.node_id.clone(), format!("127.0.0.1:{}", server_state.port))));
+
+        let mut peer_ids = server_state.peer_table.lock().unwrap().peer_ids();
+        peer_ids.sort();
+        assert_eq!(peer_ids, vec!["peer-a".to_owned(), "peer-b".to_owned()]);
+    }
+
+    #[tokio::test]
+    async fn test_cluster_rejects_unsupported_subcommand() {
+        let server_state = server_state::ServerState::new(None, 6379);
+        let message = protocol::array(vec![
+            protocol::bulk_string("CLUSTER"),
+            protocol::bulk_string("NOSUCHTHING"),
+        ]);
+        let cmd = Cluster { message: &message, server_state: &server_state };
+
+        assert!(cmd.execute(&storage()).await.is_err());
+    }
+}
@@ -1,30 +1,93 @@
-/// COMMAND command - returns information about available commands.
+/// COMMAND command - returns introspection data about available commands.
 ///
-/// Syntax: COMMAND
-/// Returns: +OK (simplified version, not full command metadata)
+/// Syntax: COMMAND | COMMAND COUNT | COMMAND DOCS [name...] | COMMAND INFO [name...]
+/// All variants are generated from `registry::COMMAND_SPECS`.
 
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::Mutex;
 use crate::protocol;
 use crate::storage;
-use super::RedisCommand;
+use super::{CommandFactory, DispatchContext, RedisCommand};
+use super::registry::{self, CommandSpec};
 
 /// COMMAND command implementation.
 pub struct Command<'a> {
     pub message: &'a protocol::DataType,
 }
 
-impl RedisCommand for Command<'_> {
-    fn execute(&self, _: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
-        // TODO: Should return the list of all the available commands and their documentation instead
-        Ok(vec![protocol::simple_string("OK")])
+/// Builds `Command` commands for the registry. Named for what it
+/// introspects rather than `CommandFactory`, to avoid colliding with the
+/// `CommandFactory` trait itself.
+pub struct IntrospectionFactory;
+
+impl CommandFactory for IntrospectionFactory {
+    fn create<'a>(&self, message: &'a protocol::DataType, _context: &DispatchContext<'a>) -> Box<dyn RedisCommand + 'a> {
+        Box::new(Command { message })
     }
+}
+
+/// Builds the `[name, arity, [flags...]]` array `COMMAND`/`COMMAND INFO` reply
+/// for a single command.
+fn command_info_entry(spec: &CommandSpec) -> protocol::DataType {
+    protocol::array(vec![
+        protocol::bulk_string(spec.name),
+        protocol::DataType::Integer { value: spec.arity },
+        protocol::array(spec.flags.iter().map(|flag| protocol::simple_string(flag)).collect()),
+    ])
+}
+
+/// Builds the `COMMAND DOCS` map entry for a single command: its name mapped
+/// to a small map of `summary`/`arity`/`flags`.
+fn command_docs_entry(spec: &CommandSpec) -> (protocol::DataType, protocol::DataType) {
+    let doc = protocol::DataType::Map {
+        entries: vec![
+            (protocol::bulk_string("summary"), protocol::bulk_string(spec.summary)),
+            (protocol::bulk_string("arity"), protocol::DataType::Integer { value: spec.arity }),
+            (protocol::bulk_string("flags"), protocol::array(spec.flags.iter().map(|flag| protocol::simple_string(flag)).collect())),
+        ],
+    };
+    (protocol::bulk_string(spec.name), doc)
+}
 
-    fn is_propagated_to_replicas(&self) -> bool {
-        false
+#[async_trait]
+impl RedisCommand for Command<'_> {
+    async fn execute(&self, _: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
+        let instructions: Vec<String> = self.message.as_vec()?;
+        let sub_command = instructions.get(1).map(|s| s.to_uppercase());
+
+        let reply = match sub_command.as_deref() {
+            None => protocol::array(registry::COMMAND_SPECS.iter().map(command_info_entry).collect()),
+            Some("COUNT") => protocol::DataType::Integer { value: registry::COMMAND_SPECS.len() as i64 },
+            Some("DOCS") => {
+                let names = &instructions[2..];
+                let specs: Vec<&CommandSpec> = if names.is_empty() {
+                    registry::COMMAND_SPECS.iter().collect()
+                } else {
+                    names.iter().filter_map(|name| registry::find(name)).collect()
+                };
+                protocol::DataType::Map { entries: specs.into_iter().map(command_docs_entry).collect() }
+            }
+            Some("INFO") => {
+                let names = &instructions[2..];
+                let entries = if names.is_empty() {
+                    registry::COMMAND_SPECS.iter().map(command_info_entry).collect()
+                } else {
+                    names.iter().map(|name| match registry::find(name) {
+                        Some(spec) => command_info_entry(spec),
+                        None => protocol::null(),
+                    }).collect()
+                };
+                protocol::array(entries)
+            }
+            Some(other) => return Err(anyhow::anyhow!("Unsupported COMMAND subcommand '{}'", other)),
+        };
+
+        Ok(vec![reply])
     }
 
-    fn should_always_reply(&self) -> bool {
-        false
+    fn name(&self) -> &'static str {
+        "COMMAND"
     }
 
     fn serialize(&self) -> Vec<u8> {
@@ -36,18 +99,74 @@ impl RedisCommand for Command<'_> {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_command_command() {
+    fn storage() -> Arc<Mutex<storage::Storage>> {
+        Arc::new(Mutex::new(storage::Storage::new(std::collections::HashMap::new())))
+    }
+
+    #[tokio::test]
+    async fn test_command_lists_every_registered_command() {
         let message = protocol::array(vec![protocol::bulk_string("COMMAND")]);
         let cmd = Command { message: &message };
 
-        let storage = Arc::new(std::sync::Mutex::new(storage::Storage::new(
-            std::collections::HashMap::new(),
-        )));
-        let result = cmd.execute(&storage).unwrap();
+        let result = cmd.execute(&storage()).await.unwrap();
 
         assert_eq!(result.len(), 1);
-        assert_eq!(result[0].as_string().unwrap(), "OK");
+        let entries = result[0].as_array().unwrap();
+        assert_eq!(entries.len(), registry::COMMAND_SPECS.len());
         assert!(!cmd.is_propagated_to_replicas());
     }
+
+    #[tokio::test]
+    async fn test_command_count_matches_registry_size() {
+        let message = protocol::array(vec![
+            protocol::bulk_string("COMMAND"),
+            protocol::bulk_string("COUNT"),
+        ]);
+        let cmd = Command { message: &message };
+
+        let result = cmd.execute(&storage()).await.unwrap();
+
+        assert_eq!(result, vec![protocol::DataType::Integer { value: registry::COMMAND_SPECS.len() as i64 }]);
+    }
+
+    #[tokio::test]
+    async fn test_command_info_returns_null_for_unknown_command() {
+        let message = protocol::array(vec![
+            protocol::bulk_string("COMMAND"),
+            protocol::bulk_string("INFO"),
+            protocol::bulk_string("GET"),
+            protocol::bulk_string("NOSUCHCOMMAND"),
+        ]);
+        let cmd = Command { message: &message };
+
+        let result = cmd.execute(&storage()).await.unwrap();
+
+        let entries = match &result[0] {
+            protocol::DataType::Array { elements } => elements,
+            other => panic!("expected an array, got {:?}", other),
+        };
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].as_array().unwrap()[0], "GET".to_owned());
+        assert_eq!(entries[1].as_string().unwrap(), "".to_owned());
+    }
+
+    #[tokio::test]
+    async fn test_command_docs_for_single_command() {
+        let message = protocol::array(vec![
+            protocol::bulk_string("COMMAND"),
+            protocol::bulk_string("DOCS"),
+            protocol::bulk_string("SET"),
+        ]);
+        let cmd = Command { message: &message };
+
+        let result = cmd.execute(&storage()).await.unwrap();
+
+        match &result[0] {
+            protocol::DataType::Map { entries } => {
+                assert_eq!(entries.len(), 1);
+                assert_eq!(entries[0].0, protocol::bulk_string("SET"));
+            }
+            other => panic!("Expected a Map reply, got {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,125 @@
+/// CONFIG command - reads and live-mutates server configuration.
+///
+/// Syntax: CONFIG GET <pattern> | CONFIG SET <param> <value>
+/// GET returns a flat array of parameter/value pairs matching a glob
+/// pattern; SET mutates a single parameter and replies +OK.
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+use anyhow::anyhow;
+use crate::protocol;
+use crate::storage;
+use crate::server_state;
+use super::{CommandFactory, DispatchContext, RedisCommand};
+
+/// CONFIG command implementation.
+pub struct Config<'a> {
+    pub message: &'a protocol::DataType,
+    pub server_state: &'a server_state::ServerState,
+}
+
+/// Builds `Config` commands for the registry.
+pub struct ConfigFactory;
+
+impl CommandFactory for ConfigFactory {
+    fn create<'a>(&self, message: &'a protocol::DataType, context: &DispatchContext<'a>) -> Box<dyn RedisCommand + 'a> {
+        Box::new(Config { message, server_state: context.server_state })
+    }
+}
+
+#[async_trait]
+impl RedisCommand for Config<'_> {
+    async fn execute(&self, _: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
+        let instructions: Vec<String> = self.message.as_vec()?;
+        let sub_command = instructions
+            .get(1)
+            .ok_or_else(|| anyhow!("CONFIG requires a subcommand"))?
+            .to_uppercase();
+
+        let reply = if sub_command == "GET" {
+            let pattern = instructions.get(2).ok_or_else(|| anyhow!("CONFIG GET requires a pattern"))?;
+            let config = self.server_state.config.lock().map_err(|e| anyhow!("Failed to lock config: {}", e))?;
+            let elements = config.get(pattern).into_iter()
+                .flat_map(|(name, value)| vec![protocol::bulk_string(&name), protocol::bulk_string(&value)])
+                .collect();
+            protocol::array(elements)
+        } else if sub_command == "SET" {
+            let param = instructions.get(2).ok_or_else(|| anyhow!("CONFIG SET requires a parameter"))?;
+            let value = instructions.get(3).ok_or_else(|| anyhow!("CONFIG SET requires a value"))?;
+            let mut config = self.server_state.config.lock().map_err(|e| anyhow!("Failed to lock config: {}", e))?;
+            config.set(param, value)?;
+            protocol::simple_string("OK")
+        } else {
+            return Err(anyhow!("Unsupported CONFIG subcommand '{}'", sub_command));
+        };
+
+        Ok(vec![reply])
+    }
+
+    fn name(&self) -> &'static str {
+        "CONFIG"
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        self.message.serialize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn storage() -> Arc<Mutex<storage::Storage>> {
+        Arc::new(Mutex::new(storage::Storage::new(HashMap::new())))
+    }
+
+    #[tokio::test]
+    async fn test_config_get_returns_matching_pairs() {
+        let server_state = server_state::ServerState::new(None, 6379);
+        let message = protocol::array(vec![
+            protocol::bulk_string("CONFIG"),
+            protocol::bulk_string("GET"),
+            protocol::bulk_string("maxmemory"),
+        ]);
+        let cmd = Config { message: &message, server_state: &server_state };
+
+        let result = cmd.execute(&storage()).await.unwrap();
+
+        assert_eq!(result.len(), 1);
+        let pairs = result[0].as_vec().unwrap();
+        assert_eq!(pairs, vec!["maxmemory".to_owned(), "0".to_owned()]);
+    }
+
+    #[tokio::test]
+    async fn test_config_set_mutates_live_config() {
+        let server_state = server_state::ServerState::new(None, 6379);
+        let message = protocol::array(vec![
+            protocol::bulk_string("CONFIG"),
+            protocol::bulk_string("SET"),
+            protocol::bulk_string("maxmemory"),
+            protocol::bulk_string("100mb"),
+        ]);
+        let cmd = Config { message: &message, server_state: &server_state };
+
+        let result = cmd.execute(&storage()).await.unwrap();
+        assert_eq!(result[0].as_string().unwrap(), "OK");
+
+        assert_eq!(server_state.config.lock().unwrap().maxmemory, "100mb");
+    }
+
+    #[tokio::test]
+    async fn test_config_set_rejects_unknown_parameter() {
+        let server_state = server_state::ServerState::new(None, 6379);
+        let message = protocol::array(vec![
+            protocol::bulk_string("CONFIG"),
+            protocol::bulk_string("SET"),
+            protocol::bulk_string("not-a-real-setting"),
+            protocol::bulk_string("value"),
+        ]);
+        let cmd = Config { message: &message, server_state: &server_state };
+
+        assert!(cmd.execute(&storage()).await.is_err());
+    }
+}
@@ -3,10 +3,12 @@
 /// Syntax: ECHO <message>
 /// Returns: The message back to the client
 
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::Mutex;
 use crate::protocol;
 use crate::storage;
-use super::RedisCommand;
+use super::{CommandFactory, DispatchContext, RedisCommand};
 
 /// ECHO command implementation.
 pub struct Echo<'a> {
@@ -14,8 +16,22 @@ pub struct Echo<'a> {
     pub argument: Option<&'a protocol::DataType>,
 }
 
+/// Builds `Echo` commands for the registry.
+pub struct EchoFactory;
+
+impl CommandFactory for EchoFactory {
+    fn create<'a>(&self, message: &'a protocol::DataType, _context: &DispatchContext<'a>) -> Box<dyn RedisCommand + 'a> {
+        let argument = match message {
+            protocol::DataType::Array { elements } => elements.get(1),
+            _ => None,
+        };
+        Box::new(Echo { message, argument })
+    }
+}
+
+#[async_trait]
 impl RedisCommand for Echo<'_> {
-    fn execute(&self, _: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
+    async fn execute(&self, _: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
         let mut reply: Vec<protocol::DataType> = Vec::new();
         if let Some(echo_argument) = self.argument {
             reply = vec![echo_argument.clone()];
@@ -23,12 +39,8 @@ impl RedisCommand for Echo<'_> {
         Ok(reply)
     }
 
-    fn is_propagated_to_replicas(&self) -> bool {
-        false
-    }
-
-    fn should_always_reply(&self) -> bool {
-        false
+    fn name(&self) -> &'static str {
+        "ECHO"
     }
 
     fn serialize(&self) -> Vec<u8> {
@@ -40,8 +52,8 @@ impl RedisCommand for Echo<'_> {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_echo_command_with_message() {
+    #[tokio::test]
+    async fn test_echo_command_with_message() {
         let echo_msg = protocol::bulk_string("Hello World");
         let message = protocol::array(vec![
             protocol::bulk_string("ECHO"),
@@ -58,27 +70,27 @@ mod tests {
             argument: Some(&elements[1]),
         };
 
-        let storage = Arc::new(std::sync::Mutex::new(storage::Storage::new(
+        let storage = Arc::new(Mutex::new(storage::Storage::new(
             std::collections::HashMap::new(),
         )));
-        let result = cmd.execute(&storage).unwrap();
+        let result = cmd.execute(&storage).await.unwrap();
 
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].as_string().unwrap(), "Hello World");
     }
 
-    #[test]
-    fn test_echo_command_without_message() {
+    #[tokio::test]
+    async fn test_echo_command_without_message() {
         let message = protocol::array(vec![protocol::bulk_string("ECHO")]);
         let cmd = Echo {
             message: &message,
             argument: None,
         };
 
-        let storage = Arc::new(std::sync::Mutex::new(storage::Storage::new(
+        let storage = Arc::new(Mutex::new(storage::Storage::new(
             std::collections::HashMap::new(),
         )));
-        let result = cmd.execute(&storage).unwrap();
+        let result = cmd.execute(&storage).await.unwrap();
 
         assert_eq!(result.len(), 0);
     }
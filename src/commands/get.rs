@@ -3,31 +3,41 @@
 /// Syntax: GET <key>
 /// Returns: The value at the key, or $-1\r\n if the key doesn't exist
 
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::Mutex;
 use crate::protocol;
 use crate::storage;
 use crate::error::RedisError;
-use super::RedisCommand;
+use super::{CommandFactory, DispatchContext, RedisCommand};
 
 /// GET command implementation.
 pub struct Get<'a> {
     pub message: &'a protocol::DataType,
 }
 
+/// Builds `Get` commands for the registry.
+pub struct GetFactory;
+
+impl CommandFactory for GetFactory {
+    fn create<'a>(&self, message: &'a protocol::DataType, _context: &DispatchContext<'a>) -> Box<dyn RedisCommand + 'a> {
+        Box::new(Get { message })
+    }
+}
+
+#[async_trait]
 impl RedisCommand for Get<'_> {
-    fn execute(&self, storage: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
-        let instructions: Vec<String> = self.message.as_array()?;
+    async fn execute(&self, storage: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
+        let instructions: Vec<Vec<u8>> = self.message.as_byte_array()?;
         let error = RedisError {
             message: "GET command should have one argument".to_string(),
         };
 
         let key = instructions.get(1).ok_or::<anyhow::Error>(error.clone().into())?;
 
-        println!("GET {}", key);
+        println!("GET {}", String::from_utf8_lossy(key));
 
-        let mut data = storage
-            .lock()
-            .map_err(|e| anyhow::anyhow!("Failed to lock storage: {}", e))?;
+        let mut data = storage.lock().await;
 
         let reply = match data.get(key)? {
             Some(value) => vec![protocol::bulk_string_from_bytes(value.clone())],
@@ -37,12 +47,8 @@ impl RedisCommand for Get<'_> {
         Ok(reply)
     }
 
-    fn is_propagated_to_replicas(&self) -> bool {
-        false
-    }
-
-    fn should_always_reply(&self) -> bool {
-        false
+    fn name(&self) -> &'static str {
+        "GET"
     }
 
     fn serialize(&self) -> Vec<u8> {
@@ -59,15 +65,15 @@ mod tests {
         Arc::new(Mutex::new(storage::Storage::new(HashMap::new())))
     }
 
-    fn insert_test_data(storage: &Arc<Mutex<storage::Storage>>, key: &str, value: &str) {
-        let mut data = storage.lock().unwrap();
-        let _ = data.set(key, value.as_bytes().to_vec(), None);
+    async fn insert_test_data(storage: &Arc<Mutex<storage::Storage>>, key: &str, value: &str) {
+        let mut data = storage.lock().await;
+        let _ = data.set(key.as_bytes(), value.as_bytes().to_vec(), None);
     }
 
-    #[test]
-    fn test_get_command_found() {
+    #[tokio::test]
+    async fn test_get_command_found() {
         let storage = create_test_storage();
-        insert_test_data(&storage, "mykey", "myvalue");
+        insert_test_data(&storage, "mykey", "myvalue").await;
 
         let message = protocol::array(vec![
             protocol::bulk_string("GET"),
@@ -75,15 +81,15 @@ mod tests {
         ]);
         let cmd = Get { message: &message };
 
-        let result = cmd.execute(&storage).unwrap();
+        let result = cmd.execute(&storage).await.unwrap();
 
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].as_string().unwrap(), "myvalue");
         assert!(!cmd.is_propagated_to_replicas());
     }
 
-    #[test]
-    fn test_get_command_not_found() {
+    #[tokio::test]
+    async fn test_get_command_not_found() {
         let message = protocol::array(vec![
             protocol::bulk_string("GET"),
             protocol::bulk_string("nonexistent"),
@@ -91,25 +97,25 @@ mod tests {
         let cmd = Get { message: &message };
 
         let storage = create_test_storage();
-        let result = cmd.execute(&storage).unwrap();
+        let result = cmd.execute(&storage).await.unwrap();
 
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].as_string().unwrap(), "");
     }
 
-    #[test]
-    fn test_get_command_invalid_syntax() {
+    #[tokio::test]
+    async fn test_get_command_invalid_syntax() {
         let message = protocol::array(vec![protocol::bulk_string("GET")]);
         let cmd = Get { message: &message };
 
         let storage = create_test_storage();
-        let result = cmd.execute(&storage);
+        let result = cmd.execute(&storage).await;
 
         assert!(result.is_err());
     }
 
-    #[test]
-    fn test_set_and_get_roundtrip() {
+    #[tokio::test]
+    async fn test_set_and_get_roundtrip() {
         let storage = create_test_storage();
 
         // Set a value
@@ -121,7 +127,7 @@ mod tests {
         let set_cmd = super::super::set::Set {
             message: &set_message,
         };
-        let set_result = set_cmd.execute(&storage).unwrap();
+        let set_result = set_cmd.execute(&storage).await.unwrap();
         assert_eq!(set_result[0].as_string().unwrap(), "OK");
 
         // Get the value
@@ -132,18 +138,18 @@ mod tests {
         let get_cmd = Get {
             message: &get_message,
         };
-        let get_result = get_cmd.execute(&storage).unwrap();
+        let get_result = get_cmd.execute(&storage).await.unwrap();
         assert_eq!(get_result[0].as_string().unwrap(), "test_value");
     }
 
-    #[test]
-    fn test_get_with_binary_data() {
+    #[tokio::test]
+    async fn test_get_with_binary_data() {
         let storage = create_test_storage();
 
         // Store binary data
-        let mut data = storage.lock().unwrap();
+        let mut data = storage.lock().await;
         let binary_data = vec![0u8, 1, 2, 3, 255, 254];
-        let _ = data.set("binary_key", binary_data.clone(), None);
+        let _ = data.set(b"binary_key", binary_data.clone(), None);
         drop(data);
 
         // Retrieve binary data
@@ -154,7 +160,7 @@ mod tests {
         let get_cmd = Get {
             message: &get_message,
         };
-        let result = get_cmd.execute(&storage).unwrap();
+        let result = get_cmd.execute(&storage).await.unwrap();
 
         // Verify binary data is preserved
         match &result[0] {
@@ -165,13 +171,37 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_multiple_keys() {
+    #[tokio::test]
+    async fn test_get_with_invalid_utf8_key() {
+        let storage = create_test_storage();
+
+        let invalid_utf8_key = vec![0xff, 0xfe, 0x00, 0x01];
+        let invalid_utf8_value = vec![0xc3, 0x28];
+        let mut data = storage.lock().await;
+        let _ = data.set(&invalid_utf8_key, invalid_utf8_value.clone(), None);
+        drop(data);
+
+        let get_message = protocol::array(vec![
+            protocol::bulk_string("GET"),
+            protocol::bulk_string_from_bytes(invalid_utf8_key),
+        ]);
+        let result = Get { message: &get_message }.execute(&storage).await.unwrap();
+
+        match &result[0] {
+            protocol::DataType::BulkString { value: Some(v) } => {
+                assert_eq!(v, &invalid_utf8_value);
+            }
+            _ => panic!("Expected bulk string with binary data"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multiple_keys() {
         let storage = create_test_storage();
 
         // Set multiple values
         for i in 0..5 {
-            insert_test_data(&storage, &format!("key{}", i), &format!("value{}", i));
+            insert_test_data(&storage, &format!("key{}", i), &format!("value{}", i)).await;
         }
 
         // Get each value
@@ -183,7 +213,7 @@ mod tests {
             let get_cmd = Get {
                 message: &get_message,
             };
-            let result = get_cmd.execute(&storage).unwrap();
+            let result = get_cmd.execute(&storage).await.unwrap();
             assert_eq!(result[0].as_string().unwrap(), format!("value{}", i));
         }
     }
@@ -4,12 +4,14 @@
 /// Currently supports: replication
 /// Returns: Information about the specified section
 
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::Mutex;
 use crate::protocol;
 use crate::storage;
 use crate::server_state;
 use crate::error::RedisError;
-use super::RedisCommand;
+use super::{CommandFactory, DispatchContext, RedisCommand};
 
 /// INFO command implementation.
 pub struct Info<'a> {
@@ -17,8 +19,18 @@ pub struct Info<'a> {
     pub server_state: &'a server_state::ServerState,
 }
 
+/// Builds `Info` commands for the registry.
+pub struct InfoFactory;
+
+impl CommandFactory for InfoFactory {
+    fn create<'a>(&self, message: &'a protocol::DataType, context: &DispatchContext<'a>) -> Box<dyn RedisCommand + 'a> {
+        Box::new(Info { message, server_state: context.server_state })
+    }
+}
+
+#[async_trait]
 impl RedisCommand for Info<'_> {
-    fn execute(&self, _: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
+    async fn execute(&self, _: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
         let instructions: Vec<String> = self.message.as_vec()?;
         let error = RedisError {
             message: "INFO command should have one argument".to_string(),
@@ -57,12 +69,8 @@ impl RedisCommand for Info<'_> {
         Ok(reply)
     }
 
-    fn is_propagated_to_replicas(&self) -> bool {
-        false
-    }
-
-    fn should_always_reply(&self) -> bool {
-        false
+    fn name(&self) -> &'static str {
+        "INFO"
     }
 
     fn serialize(&self) -> Vec<u8> {
@@ -75,8 +83,8 @@ mod tests {
     use super::*;
     use std::collections::HashMap;
 
-    #[test]
-    fn test_info_replication_master() {
+    #[tokio::test]
+    async fn test_info_replication_master() {
         let server_state = server_state::ServerState::new(None, 6379);
         let message = protocol::array(vec![
             protocol::bulk_string("INFO"),
@@ -88,7 +96,7 @@ mod tests {
         };
 
         let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
-        let result = cmd.execute(&storage).unwrap();
+        let result = cmd.execute(&storage).await.unwrap();
 
         assert_eq!(result.len(), 1);
         let info = result[0].as_string().unwrap();
@@ -96,8 +104,8 @@ mod tests {
         assert!(info.contains("master_replid"));
     }
 
-    #[test]
-    fn test_info_replication_slave() {
+    #[tokio::test]
+    async fn test_info_replication_slave() {
         let server_state = server_state::ServerState::new(Some("localhost 6379".to_owned()), 6380);
         let message = protocol::array(vec![
             protocol::bulk_string("INFO"),
@@ -109,7 +117,7 @@ mod tests {
         };
 
         let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
-        let result = cmd.execute(&storage).unwrap();
+        let result = cmd.execute(&storage).await.unwrap();
 
         assert_eq!(result.len(), 1);
         let info = result[0].as_string().unwrap();
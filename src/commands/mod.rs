@@ -3,8 +3,12 @@
 /// This module defines the interface for Redis commands and exports
 /// all available command implementations.
 
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::Mutex;
 use crate::protocol;
+use crate::secure_transport;
+use crate::server_state::ServerState;
 use crate::storage;
 
 pub mod echo;
@@ -15,6 +19,16 @@ pub mod get;
 pub mod info;
 pub mod replconf;
 pub mod psync;
+pub mod wait;
+pub mod bgrewriteaof;
+pub mod config;
+pub mod registry;
+pub mod cluster;
+pub mod subscribe;
+pub mod unsubscribe;
+pub mod psubscribe;
+pub mod punsubscribe;
+pub mod publish;
 
 // Re-export all command types for convenience
 pub use echo::Echo;
@@ -25,26 +39,95 @@ pub use get::Get;
 pub use info::Info;
 pub use replconf::ReplConf;
 pub use psync::PSync;
+pub use wait::Wait;
+pub use bgrewriteaof::BgRewriteAof;
+pub use config::Config;
+pub use cluster::Cluster;
+pub use subscribe::Subscribe;
+pub use unsubscribe::Unsubscribe;
+pub use psubscribe::PSubscribe;
+pub use punsubscribe::PUnsubscribe;
+pub use publish::Publish;
 
 /// Trait for implementing Redis commands.
 ///
 /// All Redis commands must implement this trait to be handled by the server.
+///
+/// `execute` is `async` so a single task can drive many connections off
+/// readiness notifications instead of blocking an OS thread per connection:
+/// commands that need to wait on something (e.g. `WAIT` for replica acks)
+/// `.await` rather than spin-block, and `storage` is guarded by an
+/// async-aware lock so acquiring it never parks the executor thread.
+#[async_trait]
 pub trait RedisCommand {
     /// Execute the command and return response(s) to send to the client.
-    fn execute(&self, storage: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error>;
-    
+    async fn execute(&self, storage: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error>;
+
+    /// The command's name as registered in `registry::COMMAND_SPECS` (e.g. `"SET"`).
+    fn name(&self) -> &'static str;
+
     /// Whether this command should be propagated to replica servers.
-    fn is_propagated_to_replicas(&self) -> bool;
-    
+    ///
+    /// Defaults to whatever `registry::COMMAND_SPECS` declares for `name()`,
+    /// so this no longer needs to be hand-duplicated in every command impl.
+    fn is_propagated_to_replicas(&self) -> bool {
+        registry::find(self.name()).map(|spec| spec.propagates_to_replicas).unwrap_or(false)
+    }
+
     /// Whether to send a response even if this is a replica receiving replicated commands.
-    fn should_always_reply(&self) -> bool;
-    
+    ///
+    /// Defaults to whatever `registry::COMMAND_SPECS` declares for `name()`.
+    fn should_always_reply(&self) -> bool {
+        registry::find(self.name()).map(|spec| spec.always_reply).unwrap_or(false)
+    }
+
     /// Serialize this command to its RESP protocol representation.
     fn serialize(&self) -> Vec<u8>;
+
+    /// Called once, immediately after this command is constructed for a
+    /// connection and before `execute`, so connection-level side effects
+    /// live on the command itself instead of being special-cased in
+    /// `connection::handle_connection`'s dispatch loop.
+    ///
+    /// Defaults to a no-op; `PSYNC` overrides it to register the connection
+    /// as a replica link, and `SUBSCRIBE`/`PSUBSCRIBE` override it to
+    /// register the connection in the Pub/Sub connection table. Takes the
+    /// connection's `WriteHandle` rather than a raw `TcpStream` so those
+    /// registrations keep writing through `secure_transport`'s encryption
+    /// when it's in use, the same handle `connection::handle_connection` sends its own
+    /// replies through.
+    fn on_connection(&self, _stream: &secure_transport::WriteHandle, _server_state: &Arc<ServerState>) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+}
+
+/// Everything beyond the message itself a `CommandFactory` might need to
+/// build a command for the connection that received it.
+pub struct DispatchContext<'a> {
+    pub server_state: &'a Arc<ServerState>,
+    /// Total replication bytes this connection has consumed so far,
+    /// including the message currently being dispatched. Only meaningful to
+    /// `REPLCONF`, which reports it back via `REPLCONF ACK` when asked.
+    pub replica_offset: usize,
+    /// The peer address of the connection this message arrived on, used by
+    /// `REPLCONF ACK` to attribute an acknowledgement to the right replica.
+    pub peer_address: Option<String>,
+}
+
+/// Builds a `Box<dyn RedisCommand>` from a received message, so
+/// `connection::handle_connection`'s dispatch loop never needs to know a command's
+/// concrete type. One factory instance per command name is built once at
+/// startup (see `registry::build`) and reused for the life of the server.
+pub trait CommandFactory: Send + Sync {
+    fn create<'a>(&self, message: &'a protocol::DataType, context: &DispatchContext<'a>) -> Box<dyn RedisCommand + 'a>;
 }
 
 /// Parses the command name from a received message.
 ///
+/// Only the command name (the first element of the array) is decoded to a
+/// `String`, and lossily so: the remaining arguments are left as raw bytes,
+/// since keys and values are binary-safe and may not be valid UTF-8.
+///
 /// # Arguments
 /// * `received_message` - The parsed RESP message (should be an array)
 ///
@@ -52,11 +135,12 @@ pub trait RedisCommand {
 /// The command name (first element of the array) or empty string if not an array
 ///
 /// # Errors
-/// Returns error if message cannot be converted to array
+/// Returns error if message cannot be converted to a byte array
 pub fn parse_command_name(received_message: &protocol::DataType) -> Result<String, anyhow::Error> {
-    let received_message_parts: Vec<String> = received_message.as_vec()?;
-    let command_parts: Vec<&str> = received_message_parts.iter().map(|x| x.as_str()).collect();
-    let command_name = command_parts.get(0).unwrap_or(&"").to_string();
+    let received_message_parts: Vec<Vec<u8>> = received_message.as_byte_array()?;
+    let command_name = received_message_parts.get(0)
+        .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+        .unwrap_or_default();
     Ok(command_name)
 }
 
@@ -3,27 +3,35 @@
 /// Syntax: PING
 /// Returns: +PONG
 
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::Mutex;
 use crate::protocol;
 use crate::storage;
-use super::RedisCommand;
+use super::{CommandFactory, DispatchContext, RedisCommand};
 
 /// PING command implementation.
 pub struct Ping<'a> {
     pub message: &'a protocol::DataType,
 }
 
-impl RedisCommand for Ping<'_> {
-    fn execute(&self, _: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
-        Ok(vec![protocol::simple_string("PONG")])
+/// Builds `Ping` commands for the registry.
+pub struct PingFactory;
+
+impl CommandFactory for PingFactory {
+    fn create<'a>(&self, message: &'a protocol::DataType, _context: &DispatchContext<'a>) -> Box<dyn RedisCommand + 'a> {
+        Box::new(Ping { message })
     }
+}
 
-    fn is_propagated_to_replicas(&self) -> bool {
-        false
+#[async_trait]
+impl RedisCommand for Ping<'_> {
+    async fn execute(&self, _: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
+        Ok(vec![protocol::simple_string("PONG")])
     }
 
-    fn should_always_reply(&self) -> bool {
-        false
+    fn name(&self) -> &'static str {
+        "PING"
     }
 
     fn serialize(&self) -> Vec<u8> {
@@ -35,15 +43,15 @@ impl RedisCommand for Ping<'_> {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_ping_command() {
+    #[tokio::test]
+    async fn test_ping_command() {
         let message = protocol::array(vec![protocol::bulk_string("PING")]);
         let cmd = Ping { message: &message };
 
-        let storage = Arc::new(std::sync::Mutex::new(storage::Storage::new(
+        let storage = Arc::new(Mutex::new(storage::Storage::new(
             std::collections::HashMap::new(),
         )));
-        let result = cmd.execute(&storage).unwrap();
+        let result = cmd.execute(&storage).await.unwrap();
 
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].as_string().unwrap(), "PONG");
@@ -0,0 +1,121 @@
+/// PSUBSCRIBE command - subscribes this connection to one or more glob
+/// patterns, matching it against every channel a later `PUBLISH` targets.
+///
+/// Syntax: PSUBSCRIBE <pattern> [pattern ...]
+/// Returns one `psubscribe`/`<pattern>`/`<count>` push frame per pattern,
+/// `<count>` being this connection's total channel-plus-pattern subscription
+/// count after that pattern is added.
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+use anyhow::anyhow;
+use crate::protocol;
+use crate::secure_transport;
+use crate::storage;
+use crate::server_state;
+use super::{CommandFactory, DispatchContext, RedisCommand};
+
+/// PSUBSCRIBE command implementation.
+pub struct PSubscribe<'a> {
+    pub message: &'a protocol::DataType,
+    pub server_state: &'a server_state::ServerState,
+    pub peer_address: String,
+}
+
+/// Builds `PSubscribe` commands for the registry.
+pub struct PSubscribeFactory;
+
+impl CommandFactory for PSubscribeFactory {
+    fn create<'a>(&self, message: &'a protocol::DataType, context: &DispatchContext<'a>) -> Box<dyn RedisCommand + 'a> {
+        Box::new(PSubscribe {
+            message,
+            server_state: context.server_state,
+            peer_address: context.peer_address.clone().unwrap_or_default(),
+        })
+    }
+}
+
+#[async_trait]
+impl RedisCommand for PSubscribe<'_> {
+    async fn execute(&self, _: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
+        let patterns: Vec<String> = self.message.as_vec()?.into_iter().skip(1).collect();
+        if patterns.is_empty() {
+            return Err(anyhow!("PSUBSCRIBE requires at least one pattern"));
+        }
+
+        let mut reply = Vec::new();
+        for pattern in patterns {
+            let count = self.server_state.psubscribe(&self.peer_address, &pattern)?;
+            reply.push(protocol::push(vec![
+                protocol::bulk_string("psubscribe"),
+                protocol::bulk_string(&pattern),
+                protocol::DataType::Integer { value: count as i64 },
+            ]));
+        }
+        Ok(reply)
+    }
+
+    fn name(&self) -> &'static str {
+        "PSUBSCRIBE"
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        self.message.serialize()
+    }
+
+    /// Registers this connection in the Pub/Sub connection table, just like
+    /// `SUBSCRIBE` does.
+    fn on_connection(&self, stream: &secure_transport::WriteHandle, server_state: &Arc<server_state::ServerState>) -> Result<(), anyhow::Error> {
+        server_state.register_pubsub_connection(Arc::clone(stream), self.peer_address.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::net::{TcpListener, TcpStream};
+
+    #[tokio::test]
+    async fn test_psubscribe_to_one_pattern_returns_count_one() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server_side, _) = listener.accept().unwrap();
+        let peer_address = server_side.peer_addr().unwrap().to_string();
+
+        let server_state = Arc::new(server_state::ServerState::new(None, 6379));
+        let message = protocol::array(vec![
+            protocol::bulk_string("PSUBSCRIBE"),
+            protocol::bulk_string("news.*"),
+        ]);
+        let cmd = PSubscribe { message: &message, server_state: &server_state, peer_address: peer_address.clone() };
+        cmd.on_connection(&secure_transport::plain_handle(server_side), &server_state).unwrap();
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        let result = cmd.execute(&storage).await.unwrap();
+
+        assert_eq!(
+            result,
+            vec![protocol::push(vec![
+                protocol::bulk_string("psubscribe"),
+                protocol::bulk_string("news.*"),
+                protocol::DataType::Integer { value: 1 },
+            ])]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_psubscribe_requires_at_least_one_pattern() {
+        let server_state = server_state::ServerState::new(None, 6379);
+        let message = protocol::array(vec![protocol::bulk_string("PSUBSCRIBE")]);
+        let cmd = PSubscribe {
+            message: &message,
+            server_state: &server_state,
+            peer_address: "127.0.0.1:1".to_owned(),
+        };
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        assert!(cmd.execute(&storage).await.is_err());
+    }
+}
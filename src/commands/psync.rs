@@ -1,38 +1,63 @@
-/// PSYNC command - partial resynchronization for replication.
+/// PSYNC command - full and partial resynchronization for replication.
 ///
 /// Syntax: PSYNC <replication_id> <offset>
-/// Returns: +FULLRESYNC <replication_id> <offset> followed by RDB
+/// Returns:
+///   - `+CONTINUE <replication_id>` followed by the backlog bytes from
+///     `<offset>` onward, if `<replication_id>` matches ours and `<offset>`
+///     is still retained in the replication backlog.
+///   - `+FULLRESYNC <replication_id> <offset>[ capa=<tokens>]` followed by an
+///     RDB snapshot, otherwise. The `capa=` suffix, present only when this
+///     connection advertised at least one capability via `REPLCONF capa`
+///     beforehand, echoes back what was negotiated.
 
-use std::sync::{Arc, Mutex};
+use std::collections::HashSet;
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::Mutex;
 use anyhow::anyhow;
 use log::*;
 use crate::protocol;
+use crate::secure_transport;
 use crate::storage;
 use crate::server_state;
-use super::RedisCommand;
+use super::{CommandFactory, DispatchContext, RedisCommand};
 
 /// PSYNC command implementation.
 pub struct PSync<'a> {
     pub message: &'a protocol::DataType,
     pub server_state: &'a server_state::ServerState,
+    /// The peer address of the connection PSYNC was received on, used to
+    /// look up the capabilities this replica advertised via `REPLCONF capa`
+    /// before sending it.
+    pub peer_address: Option<String>,
 }
 
+/// Builds `PSync` commands for the registry.
+pub struct PSyncFactory;
+
+impl CommandFactory for PSyncFactory {
+    fn create<'a>(&self, message: &'a protocol::DataType, context: &DispatchContext<'a>) -> Box<dyn RedisCommand + 'a> {
+        Box::new(PSync { message, server_state: context.server_state, peer_address: context.peer_address.clone() })
+    }
+}
+
+#[async_trait]
 impl RedisCommand for PSync<'_> {
-    fn execute(&self, storage: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
+    async fn execute(&self, storage: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
         let mut reply = Vec::new();
         let instructions: Vec<String> = self.message.as_array()?;
 
-        let replication_id = instructions
+        let requested_replication_id = instructions
             .get(1)
             .ok_or(anyhow!("replication_id not defined in {:?}", instructions))?;
-        let offset: i64 = instructions
+        let requested_offset: i64 = instructions
             .get(2)
             .ok_or(anyhow!("offset is not defined in {:?}", instructions))?
             .parse()?;
 
         info!(
             "Master handling PSYNC: replication_id = {}, offset = {}",
-            replication_id, offset
+            requested_replication_id, requested_offset
         );
 
         let replication_id = self
@@ -41,33 +66,79 @@ impl RedisCommand for PSync<'_> {
             .clone()
             .ok_or(anyhow!("replication_id is not defined on the master node"))?;
 
-        reply.push(protocol::simple_string(
-            format!("FULLRESYNC {} 0", replication_id).as_str(),
-        ));
+        let advertised_capabilities = match &self.peer_address {
+            Some(peer_address) => self.server_state.advertised_capabilities(peer_address)?,
+            None => HashSet::new(),
+        };
+        let mut sorted_capabilities: Vec<&str> = advertised_capabilities.iter().map(String::as_str).collect();
+        sorted_capabilities.sort();
+        let capability_suffix = if sorted_capabilities.is_empty() {
+            String::new()
+        } else {
+            format!(" capa={}", sorted_capabilities.join(","))
+        };
 
-        let rdb_bytes = storage
+        let backlog = self
+            .server_state
+            .replication_backlog
             .lock()
-            .map_err(|e| anyhow!("Failed to lock storage: {}", e))?
-            .to_rdb()?;
-        reply.push(protocol::DataType::Rdb { value: rdb_bytes });
+            .map_err(|e| anyhow!("Failed to lock replication backlog: {}", e))?;
 
-        //TODO: In practice it would be OK to send this command, but it fails some test expectations on Codecrafters, commenting out temporarily
-        //reply.push(protocol::array(vec![protocol::bulk_string("REPLCONF"), protocol::bulk_string("GETACK"), protocol::bulk_string("*")]));
+        let can_partially_resync = requested_replication_id == &replication_id && requested_offset >= 0;
+        let partial_resync_bytes = if can_partially_resync {
+            backlog.slice_from(requested_offset as usize)
+        } else {
+            None
+        };
 
-        Ok(reply)
-    }
+        if let Some(backlog_bytes) = partial_resync_bytes {
+            reply.push(protocol::simple_string(
+                format!("CONTINUE {}", replication_id).as_str(),
+            ));
+            if !backlog_bytes.is_empty() {
+                reply.push(protocol::DataType::Rdb { value: backlog_bytes });
+            }
+        } else {
+            let current_offset = backlog.current_offset();
+            reply.push(protocol::simple_string(
+                format!("FULLRESYNC {} {}{}", replication_id, current_offset, capability_suffix).as_str(),
+            ));
+
+            let rdb_bytes = storage.lock().await.to_rdb()?;
+            reply.push(protocol::DataType::Rdb { value: rdb_bytes });
+        }
 
-    fn is_propagated_to_replicas(&self) -> bool {
-        false
+        Ok(reply)
     }
 
-    fn should_always_reply(&self) -> bool {
-        false
+    fn name(&self) -> &'static str {
+        "PSYNC"
     }
 
     fn serialize(&self) -> Vec<u8> {
         self.message.serialize()
     }
+
+    /// Registers this connection as a replica link, moving the side effect
+    /// that used to live ad-hoc in `connection_handler`'s `PSYNC` branch onto
+    /// the command itself. Also carries over whether this replica negotiated
+    /// the `eof` capability, so the connection's `eof_framing` flag is set
+    /// before any reply goes out on it.
+    fn on_connection(&self, stream: &secure_transport::WriteHandle, server_state: &Arc<server_state::ServerState>) -> Result<(), anyhow::Error> {
+        let eof_framing = match &self.peer_address {
+            Some(peer_address) => server_state.advertised_capabilities(peer_address)?.contains("eof"),
+            None => false,
+        };
+        let peer_address = self.peer_address.clone().unwrap_or_default();
+        let mut replica_connection = server_state::ReplicaConnection::new(Arc::clone(stream), peer_address);
+        replica_connection.eof_framing = eof_framing;
+        server_state
+            .replica_connections
+            .lock()
+            .map_err(|e| anyhow!("Failed to lock replica connections: {}", e))?
+            .push(replica_connection);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -75,8 +146,8 @@ mod tests {
     use super::*;
     use std::collections::HashMap;
 
-    #[test]
-    fn test_psync_returns_fullresync() {
+    #[tokio::test]
+    async fn test_psync_returns_fullresync() {
         let server_state = server_state::ServerState::new(None, 6379);
         let message = protocol::array(vec![
             protocol::bulk_string("PSYNC"),
@@ -86,10 +157,11 @@ mod tests {
         let cmd = PSync {
             message: &message,
             server_state: &server_state,
+            peer_address: None,
         };
 
         let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
-        let result = cmd.execute(&storage).unwrap();
+        let result = cmd.execute(&storage).await.unwrap();
 
         assert_eq!(result.len(), 2);
         let fullresync = result[0].as_string().unwrap();
@@ -103,4 +175,131 @@ mod tests {
             _ => panic!("Expected RDB data type"),
         }
     }
+
+    #[tokio::test]
+    async fn test_psync_returns_continue_when_offset_still_in_backlog() {
+        let server_state = server_state::ServerState::new(None, 6379);
+        server_state.record_propagated_bytes(b"*1\r\n$4\r\nPING\r\n").unwrap();
+        let replication_id = server_state.master_replication_id.clone().unwrap();
+
+        let message = protocol::array(vec![
+            protocol::bulk_string("PSYNC"),
+            protocol::bulk_string(&replication_id),
+            protocol::bulk_string("0"),
+        ]);
+        let cmd = PSync {
+            message: &message,
+            server_state: &server_state,
+            peer_address: None,
+        };
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        let result = cmd.execute(&storage).await.unwrap();
+
+        let reply = result[0].as_string().unwrap();
+        assert!(reply.starts_with(&format!("CONTINUE {}", replication_id)));
+        assert_eq!(result[1].as_string().unwrap(), "*1\r\n$4\r\nPING\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_psync_falls_back_to_fullresync_when_offset_has_fallen_out_of_the_backlog_window() {
+        let server_state = server_state::ServerState::new(None, 6379);
+        // Force the backlog to evict its oldest byte by filling it past its
+        // default 1 MiB capacity, so offset 0 is no longer retained.
+        server_state.record_propagated_bytes(&vec![b'x'; 1024 * 1024 + 1]).unwrap();
+        let replication_id = server_state.master_replication_id.clone().unwrap();
+
+        let message = protocol::array(vec![
+            protocol::bulk_string("PSYNC"),
+            protocol::bulk_string(&replication_id),
+            protocol::bulk_string("0"),
+        ]);
+        let cmd = PSync {
+            message: &message,
+            server_state: &server_state,
+            peer_address: None,
+        };
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        let result = cmd.execute(&storage).await.unwrap();
+
+        assert!(result[0].as_string().unwrap().starts_with("FULLRESYNC"));
+    }
+
+    #[tokio::test]
+    async fn test_psync_falls_back_to_fullresync_when_replication_id_mismatches() {
+        let server_state = server_state::ServerState::new(None, 6379);
+        server_state.record_propagated_bytes(b"*1\r\n$4\r\nPING\r\n").unwrap();
+
+        let message = protocol::array(vec![
+            protocol::bulk_string("PSYNC"),
+            protocol::bulk_string("unknown-replication-id"),
+            protocol::bulk_string("0"),
+        ]);
+        let cmd = PSync {
+            message: &message,
+            server_state: &server_state,
+            peer_address: None,
+        };
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        let result = cmd.execute(&storage).await.unwrap();
+
+        assert!(result[0].as_string().unwrap().starts_with("FULLRESYNC"));
+    }
+
+    #[tokio::test]
+    async fn test_psync_echoes_the_peers_negotiated_capabilities_in_the_fullresync_line() {
+        let server_state = server_state::ServerState::new(None, 6379);
+        let peer_address = "127.0.0.1:9999".to_owned();
+        server_state.record_advertised_capability(&peer_address, "psync2").unwrap();
+        server_state.record_advertised_capability(&peer_address, "eof").unwrap();
+
+        let message = protocol::array(vec![
+            protocol::bulk_string("PSYNC"),
+            protocol::bulk_string("?"),
+            protocol::bulk_string("-1"),
+        ]);
+        let cmd = PSync {
+            message: &message,
+            server_state: &server_state,
+            peer_address: Some(peer_address),
+        };
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        let result = cmd.execute(&storage).await.unwrap();
+
+        let fullresync = result[0].as_string().unwrap();
+        assert!(fullresync.starts_with("FULLRESYNC"));
+        assert!(fullresync.contains("capa=eof,psync2"));
+    }
+
+    #[tokio::test]
+    async fn test_psync_on_connection_flags_the_replica_as_eof_capable_when_negotiated() {
+        use std::net::{TcpListener, TcpStream};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server_side, _) = listener.accept().unwrap();
+        let peer_address = server_side.peer_addr().unwrap().to_string();
+
+        let server_state = Arc::new(server_state::ServerState::new(None, 6379));
+        server_state.record_advertised_capability(&peer_address, "eof").unwrap();
+
+        let message = protocol::array(vec![
+            protocol::bulk_string("PSYNC"),
+            protocol::bulk_string("?"),
+            protocol::bulk_string("-1"),
+        ]);
+        let cmd = PSync {
+            message: &message,
+            server_state: &server_state,
+            peer_address: Some(peer_address),
+        };
+
+        cmd.on_connection(&secure_transport::plain_handle(server_side), &server_state).unwrap();
+
+        let replicas = server_state.replica_connections.lock().unwrap();
+        assert!(replicas[0].eof_framing);
+    }
 }
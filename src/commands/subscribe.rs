@@ -0,0 +1,127 @@
+/// SUBSCRIBE command - subscribes this connection to one or more Pub/Sub
+/// channels.
+///
+/// Syntax: SUBSCRIBE <channel> [channel ...]
+/// Returns one `subscribe`/`<channel>`/`<count>` push frame per channel,
+/// `<count>` being this connection's total channel-plus-pattern subscription
+/// count after that channel is added.
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+use anyhow::anyhow;
+use crate::protocol;
+use crate::secure_transport;
+use crate::storage;
+use crate::server_state;
+use super::{CommandFactory, DispatchContext, RedisCommand};
+
+/// SUBSCRIBE command implementation.
+pub struct Subscribe<'a> {
+    pub message: &'a protocol::DataType,
+    pub server_state: &'a server_state::ServerState,
+    pub peer_address: String,
+}
+
+/// Builds `Subscribe` commands for the registry.
+pub struct SubscribeFactory;
+
+impl CommandFactory for SubscribeFactory {
+    fn create<'a>(&self, message: &'a protocol::DataType, context: &DispatchContext<'a>) -> Box<dyn RedisCommand + 'a> {
+        Box::new(Subscribe {
+            message,
+            server_state: context.server_state,
+            peer_address: context.peer_address.clone().unwrap_or_default(),
+        })
+    }
+}
+
+#[async_trait]
+impl RedisCommand for Subscribe<'_> {
+    async fn execute(&self, _: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
+        let channels: Vec<String> = self.message.as_vec()?.into_iter().skip(1).collect();
+        if channels.is_empty() {
+            return Err(anyhow!("SUBSCRIBE requires at least one channel"));
+        }
+
+        let mut reply = Vec::new();
+        for channel in channels {
+            let count = self.server_state.subscribe(&self.peer_address, &channel)?;
+            reply.push(protocol::push(vec![
+                protocol::bulk_string("subscribe"),
+                protocol::bulk_string(&channel),
+                protocol::DataType::Integer { value: count as i64 },
+            ]));
+        }
+        Ok(reply)
+    }
+
+    fn name(&self) -> &'static str {
+        "SUBSCRIBE"
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        self.message.serialize()
+    }
+
+    /// Registers this connection in the Pub/Sub connection table, moving the
+    /// side effect `PUBLISH` needs onto the command itself, the same way
+    /// `PSYNC` registers a replica link.
+    fn on_connection(&self, stream: &secure_transport::WriteHandle, server_state: &Arc<server_state::ServerState>) -> Result<(), anyhow::Error> {
+        server_state.register_pubsub_connection(Arc::clone(stream), self.peer_address.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_subscribe_to_one_channel_returns_count_one() {
+        use std::net::{TcpListener, TcpStream};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server_side, _) = listener.accept().unwrap();
+        let peer_address = server_side.peer_addr().unwrap().to_string();
+
+        let server_state = Arc::new(server_state::ServerState::new(None, 6379));
+        let message = protocol::array(vec![
+            protocol::bulk_string("SUBSCRIBE"),
+            protocol::bulk_string("news"),
+        ]);
+        let cmd = Subscribe {
+            message: &message,
+            server_state: &server_state,
+            peer_address: peer_address.clone(),
+        };
+        cmd.on_connection(&secure_transport::plain_handle(server_side), &server_state).unwrap();
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        let result = cmd.execute(&storage).await.unwrap();
+
+        assert_eq!(
+            result,
+            vec![protocol::push(vec![
+                protocol::bulk_string("subscribe"),
+                protocol::bulk_string("news"),
+                protocol::DataType::Integer { value: 1 },
+            ])]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_requires_at_least_one_channel() {
+        let server_state = server_state::ServerState::new(None, 6379);
+        let message = protocol::array(vec![protocol::bulk_string("SUBSCRIBE")]);
+        let cmd = Subscribe {
+            message: &message,
+            server_state: &server_state,
+            peer_address: "127.0.0.1:1".to_owned(),
+        };
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        assert!(cmd.execute(&storage).await.is_err());
+    }
+}
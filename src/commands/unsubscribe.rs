@@ -0,0 +1,134 @@
+/// UNSUBSCRIBE command - unsubscribes this connection from one or more
+/// Pub/Sub channels, or from all of them if none are given.
+///
+/// Syntax: UNSUBSCRIBE [channel ...]
+/// Returns one `unsubscribe`/`<channel>`/`<count>` push frame per channel
+/// removed, `<count>` being this connection's remaining channel-plus-pattern
+/// subscription count.
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+use crate::protocol;
+use crate::storage;
+use crate::server_state;
+use super::{CommandFactory, DispatchContext, RedisCommand};
+
+/// UNSUBSCRIBE command implementation.
+pub struct Unsubscribe<'a> {
+    pub message: &'a protocol::DataType,
+    pub server_state: &'a server_state::ServerState,
+    pub peer_address: String,
+}
+
+/// Builds `Unsubscribe` commands for the registry.
+pub struct UnsubscribeFactory;
+
+impl CommandFactory for UnsubscribeFactory {
+    fn create<'a>(&self, message: &'a protocol::DataType, context: &DispatchContext<'a>) -> Box<dyn RedisCommand + 'a> {
+        Box::new(Unsubscribe {
+            message,
+            server_state: context.server_state,
+            peer_address: context.peer_address.clone().unwrap_or_default(),
+        })
+    }
+}
+
+#[async_trait]
+impl RedisCommand for Unsubscribe<'_> {
+    async fn execute(&self, _: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
+        let requested_channels: Vec<String> = self.message.as_vec()?.into_iter().skip(1).collect();
+        let channels = if requested_channels.is_empty() {
+            self.server_state.subscribed_channels(&self.peer_address)?
+        } else {
+            requested_channels
+        };
+
+        if channels.is_empty() {
+            return Ok(vec![protocol::push(vec![
+                protocol::bulk_string("unsubscribe"),
+                protocol::bulk_string_empty(),
+                protocol::DataType::Integer { value: 0 },
+            ])]);
+        }
+
+        let mut reply = Vec::new();
+        for channel in channels {
+            let count = self.server_state.unsubscribe(&self.peer_address, &channel)?;
+            reply.push(protocol::push(vec![
+                protocol::bulk_string("unsubscribe"),
+                protocol::bulk_string(&channel),
+                protocol::DataType::Integer { value: count as i64 },
+            ]));
+        }
+        Ok(reply)
+    }
+
+    fn name(&self) -> &'static str {
+        "UNSUBSCRIBE"
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        self.message.serialize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::net::TcpListener;
+    use std::net::TcpStream;
+
+    fn connected_peer_address(server_state: &Arc<server_state::ServerState>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server_side, _) = listener.accept().unwrap();
+        let peer_address = server_side.peer_addr().unwrap().to_string();
+        server_state.register_pubsub_connection(crate::secure_transport::plain_handle(server_side), peer_address.clone()).unwrap();
+        peer_address
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe_from_named_channel_decrements_count() {
+        let server_state = Arc::new(server_state::ServerState::new(None, 6379));
+        let peer_address = connected_peer_address(&server_state);
+        server_state.subscribe(&peer_address, "news").unwrap();
+        server_state.subscribe(&peer_address, "sports").unwrap();
+
+        let message = protocol::array(vec![
+            protocol::bulk_string("UNSUBSCRIBE"),
+            protocol::bulk_string("news"),
+        ]);
+        let cmd = Unsubscribe { message: &message, server_state: &server_state, peer_address: peer_address.clone() };
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        let result = cmd.execute(&storage).await.unwrap();
+
+        assert_eq!(
+            result,
+            vec![protocol::push(vec![
+                protocol::bulk_string("unsubscribe"),
+                protocol::bulk_string("news"),
+                protocol::DataType::Integer { value: 1 },
+            ])]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe_with_no_arguments_unsubscribes_from_everything() {
+        let server_state = Arc::new(server_state::ServerState::new(None, 6379));
+        let peer_address = connected_peer_address(&server_state);
+        server_state.subscribe(&peer_address, "news").unwrap();
+        server_state.subscribe(&peer_address, "sports").unwrap();
+
+        let message = protocol::array(vec![protocol::bulk_string("UNSUBSCRIBE")]);
+        let cmd = Unsubscribe { message: &message, server_state: &server_state, peer_address: peer_address.clone() };
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        let result = cmd.execute(&storage).await.unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(server_state.subscribed_channels(&peer_address).unwrap().len(), 0);
+    }
+}
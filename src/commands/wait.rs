@@ -0,0 +1,192 @@
+/// WAIT command - blocks until a number of replicas have acknowledged the
+/// current replication offset, or a timeout elapses.
+///
+/// Syntax: WAIT <numreplicas> <timeout_ms>
+/// Returns: the number of replicas that acknowledged the offset the master
+/// had reached when WAIT was received, as a RESP integer.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+use anyhow::anyhow;
+use crate::protocol;
+use crate::storage;
+use crate::server_state;
+use super::{CommandFactory, DispatchContext, RedisCommand};
+
+/// WAIT command implementation.
+pub struct Wait<'a> {
+    pub message: &'a protocol::DataType,
+    pub server_state: &'a server_state::ServerState,
+}
+
+/// Builds `Wait` commands for the registry.
+pub struct WaitFactory;
+
+impl CommandFactory for WaitFactory {
+    fn create<'a>(&self, message: &'a protocol::DataType, context: &DispatchContext<'a>) -> Box<dyn RedisCommand + 'a> {
+        Box::new(Wait { message, server_state: context.server_state })
+    }
+}
+
+#[async_trait]
+impl RedisCommand for Wait<'_> {
+    async fn execute(&self, _: &Arc<Mutex<storage::Storage>>) -> Result<Vec<protocol::DataType>, anyhow::Error> {
+        let instructions: Vec<String> = self.message.as_vec()?;
+        let numreplicas: usize = instructions
+            .get(1)
+            .ok_or(anyhow!("numreplicas not defined in {:?}", instructions))?
+            .parse()?;
+        let timeout_ms: u64 = instructions
+            .get(2)
+            .ok_or(anyhow!("timeout_ms not defined in {:?}", instructions))?
+            .parse()?;
+
+        let target_offset = self.server_state.current_replication_offset()?;
+        let getack = protocol::array(vec![
+            protocol::bulk_string("REPLCONF"),
+            protocol::bulk_string("GETACK"),
+            protocol::bulk_string("*"),
+        ]);
+        let getack_bytes = getack.serialize();
+        // GETACK is itself propagated over the replication stream, so it has
+        // to advance the master's offset exactly like any other propagated
+        // command: otherwise a replica's acked offset (which does include
+        // the GETACK bytes it received) would run permanently ahead of what
+        // the master thinks it sent.
+        self.server_state.record_propagated_bytes(&getack_bytes)?;
+        self.server_state.broadcast_to_replicas(&getack_bytes)?;
+
+        let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+        let mut caught_up = self.server_state.replicas_caught_up_to(target_offset)?;
+        while caught_up < numreplicas && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            caught_up = self.server_state.replicas_caught_up_to(target_offset)?;
+        }
+
+        Ok(vec![protocol::DataType::Integer { value: caught_up as i64 }])
+    }
+
+    fn name(&self) -> &'static str {
+        "WAIT"
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        self.message.serialize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_wait_with_no_replicas_and_zero_required_returns_immediately() {
+        let server_state = server_state::ServerState::new(None, 6379);
+        let message = protocol::array(vec![
+            protocol::bulk_string("WAIT"),
+            protocol::bulk_string("0"),
+            protocol::bulk_string("100"),
+        ]);
+        let cmd = Wait {
+            message: &message,
+            server_state: &server_state,
+        };
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        let result = cmd.execute(&storage).await.unwrap();
+
+        assert_eq!(result, vec![protocol::DataType::Integer { value: 0 }]);
+    }
+
+    #[tokio::test]
+    async fn test_wait_times_out_when_replicas_required_but_none_connected() {
+        let server_state = server_state::ServerState::new(None, 6379);
+        let message = protocol::array(vec![
+            protocol::bulk_string("WAIT"),
+            protocol::bulk_string("1"),
+            protocol::bulk_string("50"),
+        ]);
+        let cmd = Wait {
+            message: &message,
+            server_state: &server_state,
+        };
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        let start = Instant::now();
+        let result = cmd.execute(&storage).await.unwrap();
+
+        assert_eq!(result, vec![protocol::DataType::Integer { value: 0 }]);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_wait_returns_as_soon_as_a_replica_acks_the_captured_offset() {
+        use std::net::{TcpListener, TcpStream};
+        use crate::secure_transport;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server_side, _) = listener.accept().unwrap();
+        let peer_address = server_side.peer_addr().unwrap().to_string();
+
+        let server_state = Arc::new(server_state::ServerState::new(None, 6379));
+        server_state.replica_connections.lock().unwrap().push(server_state::ReplicaConnection::new(
+            secure_transport::plain_handle(server_side),
+            peer_address.clone(),
+        ));
+        server_state.record_propagated_bytes(b"*1\r\n$4\r\nPING\r\n").unwrap();
+        let target_offset = server_state.current_replication_offset().unwrap();
+
+        // Acknowledge the offset shortly after WAIT starts polling for it,
+        // from a separate task, the way a replica's own connection loop
+        // would report its progress via REPLCONF ACK.
+        let acking_server_state = Arc::clone(&server_state);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            acking_server_state.record_replica_ack(&peer_address, target_offset).unwrap();
+        });
+
+        let message = protocol::array(vec![
+            protocol::bulk_string("WAIT"),
+            protocol::bulk_string("1"),
+            protocol::bulk_string("1000"),
+        ]);
+        let cmd = Wait { message: &message, server_state: &server_state };
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        let start = Instant::now();
+        let result = cmd.execute(&storage).await.unwrap();
+
+        assert_eq!(result, vec![protocol::DataType::Integer { value: 1 }]);
+        // It should have returned once acked, long before the 1s timeout.
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn test_wait_advances_the_master_offset_by_the_getack_it_sends() {
+        let server_state = server_state::ServerState::new(None, 6379);
+        let offset_before = server_state.current_replication_offset().unwrap();
+        let message = protocol::array(vec![
+            protocol::bulk_string("WAIT"),
+            protocol::bulk_string("0"),
+            protocol::bulk_string("100"),
+        ]);
+        let cmd = Wait { message: &message, server_state: &server_state };
+
+        let storage = Arc::new(Mutex::new(storage::Storage::new(HashMap::new())));
+        cmd.execute(&storage).await.unwrap();
+
+        let getack = protocol::array(vec![
+            protocol::bulk_string("REPLCONF"),
+            protocol::bulk_string("GETACK"),
+            protocol::bulk_string("*"),
+        ]);
+        assert_eq!(
+            server_state.current_replication_offset().unwrap(),
+            offset_before + getack.serialize().len()
+        );
+    }
+}
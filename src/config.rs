@@ -0,0 +1,259 @@
+/// Server configuration: `redis.conf`-style file parsing merged with
+/// command-line overrides, plus live `CONFIG GET`/`CONFIG SET` access.
+///
+/// Precedence matches `redis-server`: a config file, if given as the first
+/// non-flag argument, is parsed first; any `--directive value` pairs on the
+/// command line are then applied on top of it.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use anyhow::{anyhow, Context, Error};
+
+/// The server's tunable settings. Fields are plain `String`s (mirroring how
+/// `redis-server` itself treats most `CONFIG GET`/`SET` values) except for
+/// `port`, which callers need as a number to bind the listener, and
+/// `replica_of`, which is structured the same way it is everywhere else in
+/// this codebase.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerConfig {
+    pub port: usize,
+    pub replica_of: Option<String>,
+    pub dir: String,
+    pub dbfilename: String,
+    pub appendonly: String,
+    pub appendfsync: String,
+    pub maxmemory: String,
+    pub save: String,
+    pub bind: String,
+    /// Whether inter-node links (replication and cluster gossip) and client
+    /// connections go through `secure_transport`'s authenticated handshake
+    /// instead of plaintext RESP. Mirrors `appendonly`: a `"yes"`/`"no"`
+    /// string rather than a `bool`, so it round-trips through `CONFIG
+    /// GET`/`SET` like every other setting here.
+    pub secure: String,
+    /// The pre-shared network key every node in the mesh must agree on to
+    /// complete the `secure_transport` handshake. Empty when `secure` is
+    /// `"no"`.
+    pub network_key: String,
+    /// Whether to serve connections through `async_runtime`'s multiplexed
+    /// `tokio` listener instead of the thread-per-connection loop in `main`.
+    /// A `"yes"`/`"no"` string like `appendonly`/`secure` rather than a
+    /// `bool`, so it round-trips through `CONFIG GET`/`SET` like everything
+    /// else here.
+    pub async_io: String,
+}
+
+impl Default for ServerConfig {
+    fn default() -> ServerConfig {
+        ServerConfig {
+            port: 6379,
+            replica_of: None,
+            dir: ".".to_owned(),
+            dbfilename: "dump.rdb".to_owned(),
+            appendonly: "no".to_owned(),
+            appendfsync: "everysec".to_owned(),
+            maxmemory: "0".to_owned(),
+            save: "3600 1 300 100 60 10000".to_owned(),
+            bind: "127.0.0.1".to_owned(),
+            secure: "no".to_owned(),
+            network_key: "".to_owned(),
+            async_io: "no".to_owned(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Builds a `ServerConfig` from process command-line arguments
+    /// (`args[0]` is the program name, matching `std::env::args()`). If
+    /// `args[1]` doesn't look like a `--flag`, it's treated as a config-file
+    /// path and parsed first; `--directive value` pairs anywhere after that
+    /// are then applied as overrides.
+    pub fn from_args(args: &[String]) -> Result<ServerConfig, Error> {
+        let mut directives = HashMap::new();
+        if let Some(conf_path) = args.get(1).filter(|arg| !arg.starts_with("--")) {
+            directives.extend(parse_conf_file(Path::new(conf_path))?);
+        }
+        directives.extend(parse_cli_overrides(&args[1..]));
+
+        let mut config = ServerConfig::default();
+        for (param, value) in directives.iter() {
+            config.set(param, value)?;
+        }
+        Ok(config)
+    }
+
+    /// Live-mutates a single configuration parameter, as used by both
+    /// startup parsing and `CONFIG SET`.
+    pub fn set(&mut self, param: &str, value: &str) -> Result<(), Error> {
+        match param.to_lowercase().as_str() {
+            "port" => self.port = value.parse().context("port must be a number")?,
+            "replicaof" => {
+                self.replica_of = if value.eq_ignore_ascii_case("no one") {
+                    None
+                } else {
+                    Some(value.to_owned())
+                }
+            }
+            "dir" => self.dir = value.to_owned(),
+            "dbfilename" => self.dbfilename = value.to_owned(),
+            "appendonly" => self.appendonly = value.to_lowercase(),
+            "appendfsync" => self.appendfsync = value.to_lowercase(),
+            "maxmemory" => self.maxmemory = value.to_owned(),
+            "save" => self.save = value.to_owned(),
+            "bind" => self.bind = value.to_owned(),
+            "secure" => self.secure = value.to_lowercase(),
+            "network-key" => self.network_key = value.to_owned(),
+            "async-io" => self.async_io = value.to_lowercase(),
+            other => return Err(anyhow!("Unsupported CONFIG parameter '{}'", other)),
+        }
+        Ok(())
+    }
+
+    /// Returns the `(parameter, value)` pairs whose name matches a
+    /// `CONFIG GET` glob pattern (`*` and `?` wildcards, case-insensitive).
+    pub fn get(&self, pattern: &str) -> Vec<(String, String)> {
+        self.as_pairs().into_iter().filter(|(name, _)| glob_match(pattern, name)).collect()
+    }
+
+    fn as_pairs(&self) -> Vec<(String, String)> {
+        vec![
+            ("port".to_owned(), self.port.to_string()),
+            ("dir".to_owned(), self.dir.clone()),
+            ("dbfilename".to_owned(), self.dbfilename.clone()),
+            ("appendonly".to_owned(), self.appendonly.clone()),
+            ("appendfsync".to_owned(), self.appendfsync.clone()),
+            ("maxmemory".to_owned(), self.maxmemory.clone()),
+            ("save".to_owned(), self.save.clone()),
+            ("bind".to_owned(), self.bind.clone()),
+            ("secure".to_owned(), self.secure.clone()),
+            ("network-key".to_owned(), self.network_key.clone()),
+            ("async-io".to_owned(), self.async_io.clone()),
+        ]
+    }
+}
+
+/// Parses a `redis.conf`-style file: one directive per line, the directive
+/// name and its value separated by whitespace, `#`-prefixed comments and
+/// blank lines ignored.
+fn parse_conf_file(path: &Path) -> Result<HashMap<String, String>, Error> {
+    let contents = fs::read_to_string(path).with_context(|| format!("Could not read config file {}", path.display()))?;
+    let mut directives = HashMap::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.splitn(2, char::is_whitespace);
+        let key = parts.next().unwrap_or("").to_lowercase();
+        let value = parts.next().unwrap_or("").trim().to_owned();
+        if !key.is_empty() {
+            directives.insert(key, value);
+        }
+    }
+    Ok(directives)
+}
+
+/// Scans `--directive value` pairs out of the command-line arguments.
+fn parse_cli_overrides(args: &[String]) -> HashMap<String, String> {
+    let mut overrides = HashMap::new();
+    let mut position = 0;
+    while position < args.len() {
+        if let Some(name) = args[position].strip_prefix("--") {
+            if let Some(value) = args.get(position + 1) {
+                overrides.insert(name.to_lowercase(), value.clone());
+                position += 2;
+                continue;
+            }
+        }
+        position += 1;
+    }
+    overrides
+}
+
+/// Matches `text` against a glob `pattern` made of literal characters, `*`
+/// (any run of characters) and `?` (any single character), case-insensitively.
+///
+/// `pub(crate)` so `server_state`'s `PSUBSCRIBE` pattern matching can reuse it
+/// instead of growing its own copy.
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    fn matches(pattern: &[u8], text: &[u8]) -> bool {
+        match (pattern.first(), text.first()) {
+            (None, None) => true,
+            (Some(b'*'), _) => matches(&pattern[1..], text) || (!text.is_empty() && matches(pattern, &text[1..])),
+            (Some(b'?'), Some(_)) => matches(&pattern[1..], &text[1..]),
+            (Some(p), Some(t)) if p.to_ascii_lowercase() == t.to_ascii_lowercase() => matches(&pattern[1..], &text[1..]),
+            _ => false,
+        }
+    }
+    matches(pattern.as_bytes(), text.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_apply_cli_overrides_on_top_of_defaults() {
+        let args = vec!["prog".to_owned(), "--port".to_owned(), "6380".to_owned(), "--dir".to_owned(), "/data".to_owned()];
+        let config = ServerConfig::from_args(&args).unwrap();
+        assert_eq!(config.port, 6380);
+        assert_eq!(config.dir, "/data");
+        assert_eq!(config.appendonly, "no");
+    }
+
+    #[test]
+    fn should_parse_conf_file_and_let_cli_flags_override_it() {
+        let path = std::env::temp_dir().join("redis_config_test.conf");
+        std::fs::write(&path, "# a comment\nport 6390\ndir /var/lib/redis\nappendonly yes\n").unwrap();
+
+        let args = vec!["prog".to_owned(), path.to_string_lossy().into_owned(), "--port".to_owned(), "6391".to_owned()];
+        let config = ServerConfig::from_args(&args).unwrap();
+
+        assert_eq!(config.port, 6391);
+        assert_eq!(config.dir, "/var/lib/redis");
+        assert_eq!(config.appendonly, "yes");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn should_reject_unknown_directive() {
+        let args = vec!["prog".to_owned(), "--not-a-real-setting".to_owned(), "value".to_owned()];
+        assert!(ServerConfig::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn should_set_replicaof_and_clear_it_with_no_one() {
+        let mut config = ServerConfig::default();
+        config.set("replicaof", "localhost 6379").unwrap();
+        assert_eq!(config.replica_of, Some("localhost 6379".to_owned()));
+
+        config.set("replicaof", "no one").unwrap();
+        assert_eq!(config.replica_of, None);
+    }
+
+    #[test]
+    fn should_get_pairs_matching_a_glob_pattern() {
+        let config = ServerConfig::default();
+        let mut pairs = config.get("max*");
+        pairs.sort();
+        assert_eq!(pairs, vec![("maxmemory".to_owned(), "0".to_owned())]);
+
+        let mut all = config.get("*");
+        all.sort();
+        assert_eq!(all.len(), 11);
+    }
+
+    #[test]
+    fn should_set_secure_and_network_key() {
+        let mut config = ServerConfig::default();
+        assert_eq!(config.secure, "no");
+
+        config.set("secure", "yes").unwrap();
+        config.set("network-key", "shared-network-key").unwrap();
+
+        assert_eq!(config.secure, "yes");
+        assert_eq!(config.network_key, "shared-network-key");
+    }
+}
@@ -3,17 +3,28 @@
 /// This module handles incoming TCP connections, parses commands,
 /// executes them, and sends responses back to clients.
 
-use anyhow::anyhow;
+use anyhow::{anyhow, ensure};
+use std::collections::HashSet;
 use std::io::Write;
-use std::net::TcpStream;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use bytes::Bytes;
 
 use crate::protocol::DataType;
+use crate::chunked_buffer::ChunkedBuffer;
 use crate::io;
-use crate::commands::{self, RedisCommand};
+use crate::commands::{self, registry, DispatchContext, RedisCommand};
+use crate::rdb;
+use crate::secure_transport::{TransportReader, WriteHandle};
 use crate::storage::Storage;
 use crate::server_state::ServerState;
 
+/// Size of the pieces an ingested RDB snapshot is split into before being
+/// handed to `rdb::from_rdb` through a `ChunkedBuffer`, so parsing never
+/// pulls more than one bounded chunk across the `Read` boundary at a time.
+const RDB_INGEST_CHUNK_SIZE: usize = 4096;
+
 /// Handles a single client connection.
 ///
 /// This function:
@@ -23,110 +34,119 @@ use crate::server_state::ServerState;
 /// 4. Sends responses back to the client
 /// 5. Propagates write commands to replicas if master
 ///
+/// `storage` is guarded by an async-aware lock, so awaiting a command's
+/// `execute` never parks the thread driving this connection.
+///
 /// # Arguments
-/// * `stream` - TCP stream for the client connection
+/// * `reader` - Read half of the connection's transport, already past the
+///   `secure_transport` handshake (see `secure_transport::negotiate_server`)
+/// * `writer` - Write half of the connection's transport, shared with
+///   `on_connection` registrations (`PSYNC`/`SUBSCRIBE`) so propagation and
+///   Pub/Sub publishes keep going through the same encryption, if any
 /// * `storage` - Shared storage for Redis data
 /// * `server_state` - Server state (master/replica info)
 /// * `should_reply` - Whether to send responses to this client (false for replicas during initial sync)
 ///
 /// # Returns
 /// Error if connection fails
-pub fn handle_connection(
-    stream: &mut TcpStream,
+pub async fn handle_connection(
+    reader: &mut TransportReader,
+    writer: &WriteHandle,
     storage: &Arc<Mutex<Storage>>,
     server_state: &Arc<ServerState>,
     should_reply: bool,
 ) -> Result<(), anyhow::Error> {
+    // Built once per connection and consulted by name instead of running
+    // down an `if`/`else if` chain for every message; see `registry::build`.
+    let factories = registry::build();
+    // Tracks the number of bytes received on this connection so far. When this
+    // connection is a replica link to a master, this is the offset the
+    // replica reports back via `REPLCONF GETACK`.
+    let mut received_offset: usize = 0;
+    let peer_address = reader.peer_addr().ok().map(|addr| addr.to_string());
+    // Carries leftover bytes from one read to the next so a frame split
+    // across reads is reassembled instead of being dropped or misparsed.
+    let mut read_buffer: Vec<u8> = Vec::new();
+
     loop {
-        let received_messages: Vec<DataType> = io::read_messages(stream)?;
+        let received_messages: Vec<DataType> = io::read_messages(reader, &mut read_buffer)?;
         for received_message in received_messages.into_iter() {
             println!(
                 "Received: {}",
                 String::from_utf8_lossy(&received_message.serialize()).replace("\r\n", "\\r\\n")
             );
+            let message_len = received_message.serialize().len();
             match &received_message {
-                DataType::Array { elements } => {
+                DataType::Array { .. } => {
+                    received_offset += message_len;
                     let command_name: String = commands::parse_command_name(&received_message)?;
-                    let mut command: Option<Box<dyn RedisCommand>> = None;
-                    let command_name = command_name.as_str();
-
-                    // Dispatch to appropriate command handler
-                    if command_name == "ECHO" {
-                        command = Some(Box::new(commands::Echo {
-                            message: &received_message,
-                            argument: elements.get(1),
-                        }));
-                    } else if command_name == "PING" {
-                        command = Some(Box::new(commands::Ping {
-                            message: &received_message,
-                        }));
-                    } else if command_name == "SET" {
-                        command = Some(Box::new(commands::Set {
-                            message: &received_message,
-                        }));
-                    } else if command_name == "GET" {
-                        command = Some(Box::new(commands::Get {
-                            message: &received_message,
-                        }));
-                    } else if command_name == "COMMAND" {
-                        command = Some(Box::new(commands::Command {
-                            message: &received_message,
-                        }));
-                    } else if command_name == "INFO" {
-                        command = Some(Box::new(commands::Info {
-                            message: &received_message,
-                            server_state,
-                        }));
-                    } else if command_name == "REPLCONF" {
-                        command = Some(Box::new(commands::ReplConf {
-                            message: &received_message,
-                            server_state,
-                        }));
-                    } else if command_name == "PSYNC" {
-                        command = Some(Box::new(commands::PSync {
-                            message: &received_message,
-                            server_state,
-                        }));
-                        server_state
-                            .replica_connections
-                            .lock()
-                            .map_err(|e| anyhow!("Failed to lock replica connections: {}", e))?
-                            .push(stream.try_clone()?);
-                    }
+                    let context = DispatchContext {
+                        server_state,
+                        replica_offset: received_offset,
+                        peer_address: peer_address.clone(),
+                    };
+                    let command: Option<Box<dyn RedisCommand>> = factories
+                        .get(command_name.to_uppercase().as_str())
+                        .map(|factory| factory.create(&received_message, &context));
 
                     if let Some(command) = command {
-                        let reply = command.execute(storage)?;
+                        command.on_connection(writer, server_state)?;
+                        let reply = command.execute(storage).await?;
                         if should_reply || command.should_always_reply() {
                             for message in reply.into_iter() {
                                 println!("Sending: {:?}", message);
                                 let message_bytes = &message.serialize();
                                 println!("which serializes to {:?}", message_bytes);
-                                stream.write_all(message_bytes)?;
+                                writer.lock().map_err(|e| anyhow!("Failed to lock connection writer: {}", e))?.write_all(message_bytes)?;
                             }
                         }
 
+                        if command.is_propagated_to_replicas() {
+                            server_state.append_to_aof(&command.serialize())?;
+                        }
+
                         // Propagate write commands to replicas if this is a master
                         let should_propagate_to_replicas =
                             server_state.is_master() && command.is_propagated_to_replicas();
                         if should_propagate_to_replicas {
                             let command_bytes = command.serialize();
-                            let mut replica_streams = server_state
+                            server_state.record_propagated_bytes(&command_bytes)?;
+                            let mut replicas = server_state
                                 .replica_connections
                                 .lock()
                                 .map_err(|e| anyhow!("Failed to lock replica connections: {}", e))?;
-                            for replica_stream in replica_streams.iter_mut() {
+                            for replica in replicas.iter_mut() {
                                 println!("Propagating command to replica: {:?}", &command_bytes);
-                                replica_stream.write_all(&command_bytes)?
+                                replica.stream.lock().map_err(|e| anyhow!("Failed to lock replica stream: {}", e))?.write_all(&command_bytes)?
                             }
                         }
                     }
                 }
                 DataType::Rdb { value } => {
-                    // Replica receiving RDB snapshot from master
-                    let maybe_received_storage = Storage::from_rdb(&value).ok();
+                    // Replica receiving RDB snapshot from master. `value` is
+                    // already fully in memory by the time it reaches here -
+                    // `read_messages`/`DataType::try_parse` only ever hand
+                    // back a complete frame, so this doesn't bound memory use
+                    // for the read off the wire. What draining through a
+                    // `ChunkedBuffer` instead of a single `Cursor` over the
+                    // whole slice does get us: `Bytes::from` below shares
+                    // `value`'s existing allocation rather than copying it,
+                    // so slicing it into chunks is free, and `rdb::from_rdb`
+                    // never pulls more than one bounded chunk across its
+                    // `Read` boundary at a time. Genuinely bounding the
+                    // socket read itself would mean teaching the RESP framer
+                    // to hand back a growing `ChunkedBuffer` instead of a
+                    // complete `Vec<u8>`, which is out of scope here.
+                    let bytes = Bytes::from(value.clone());
+                    let mut chunked_value = ChunkedBuffer::new();
+                    for start in (0..bytes.len()).step_by(RDB_INGEST_CHUNK_SIZE) {
+                        let end = (start + RDB_INGEST_CHUNK_SIZE).min(bytes.len());
+                        chunked_value.push(bytes.slice(start..end));
+                    }
+                    let maybe_received_storage = rdb::from_rdb(chunked_value).ok();
                     println!("Received storage {:?}", &maybe_received_storage);
                     if let Some(received_storage) = maybe_received_storage {
-                        let mut storage = storage.lock().map_err(|e| anyhow!("Failed to lock storage: {}", e))?;
+                        let mut storage = storage.lock().await;
                         for (key, value) in received_storage.data.into_iter() {
                             storage.data.insert(key, value);
                         }
@@ -145,6 +165,21 @@ pub fn handle_connection(
                             "Received replication_id {} from the master",
                             replication_id
                         );
+                        // A trailing `capa=<tokens>` part, when present, is
+                        // the master echoing back what it negotiated (see
+                        // `PSync::execute`). If the master advertised a
+                        // capa set at all, it must include `psync2` - the
+                        // one capability this replica always sends and
+                        // requires - or the handshake can't be trusted to
+                        // behave the way this client expects.
+                        if let Some(&capa_part) = reply_parts.iter().find(|part| part.starts_with("capa=")) {
+                            let master_capabilities: HashSet<&str> = capa_part.strip_prefix("capa=").unwrap_or("").split(',').collect();
+                            ensure!(
+                                master_capabilities.contains("psync2"),
+                                "Master does not support the required 'psync2' capability (advertised: {:?})",
+                                master_capabilities
+                            );
+                        }
                     }
                 }
                 _ => (),
@@ -21,4 +21,19 @@ impl fmt::Display for RedisError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "RedisError: {}", self.message)
     }
+}
+
+// Lets `RedisError` double as the error type for `resp_serde`'s
+// `Serializer`/`Deserializer` impls, so encoding/decoding RESP keeps using
+// the same error type as the rest of the protocol layer.
+impl serde::ser::Error for RedisError {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        RedisError { message: message.to_string() }
+    }
+}
+
+impl serde::de::Error for RedisError {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        RedisError { message: message.to_string() }
+    }
 }
\ No newline at end of file
@@ -1,17 +1,25 @@
-use std::{io::Read, net::TcpStream};
-use crate::protocol;
+/// Socket-facing helpers that turn raw TCP reads into parsed RESP frames.
+///
+/// A single `TcpStream::read` call may return anywhere from one byte to
+/// several frames' worth of data, and a frame can be split across multiple
+/// reads - including mid-way through a multi-byte UTF-8 sequence inside a
+/// bulk string. `read_messages` owns a persistent byte buffer across calls so
+/// partial frames are reassembled instead of failing or panicking, and
+/// `read_single_message` applies the same reassembly for the one-shot reads
+/// used during the replication handshake. Both are generic over `Read`
+/// rather than tied to `TcpStream`, so the reassembly logic can be exercised
+/// against a scripted `MockStream` in tests without a real socket.
+///
+/// Bulk strings are parsed as raw bytes under a length prefix (see
+/// `protocol::parse_bulk_string_or_rdb`), never validated as UTF-8 until
+/// something downstream calls `as_string`, so a split mid-character never
+/// needs special handling here: it's just bytes that haven't all arrived yet.
+use std::io::Read;
+use crate::protocol::{self, DataType, DecodeOutcome};
 
 const BUFFER_SIZE: usize = 2048;
 
-pub fn read_message(stream: &mut TcpStream) -> Result<Option<protocol::DataType>, anyhow::Error> {
-    if let Some(message_bytes) = read_bytes(stream)? {
-        Ok(Some(protocol::read_message_from_bytes(&message_bytes)?))
-    } else {
-        Ok(None)
-    }
-}
-
-fn read_next_bytes(stream: &mut TcpStream, buffer: &mut [u8]) -> usize {
+fn read_next_bytes(stream: &mut impl Read, buffer: &mut [u8]) -> usize {
     match stream.read(buffer) {
         Ok(read_bytes) => {
             read_bytes
@@ -22,24 +30,173 @@ fn read_next_bytes(stream: &mut TcpStream, buffer: &mut [u8]) -> usize {
     }
 }
 
-pub(crate) fn read_bytes(stream: &mut TcpStream) -> Result<Option<Vec<u8>>, anyhow::Error> {
-    let mut buffer = [0u8; BUFFER_SIZE];
-    let mut message_bytes: Vec<u8> = Vec::new();
-    let mut total_read_bytes = 0;
+/// Reads whatever is immediately available on `stream` into `buffer`, then
+/// parses as many complete RESP frames out of it as it can. Bytes belonging
+/// to a frame that hasn't fully arrived yet are left in `buffer` so the next
+/// call can pick up where this one left off.
+///
+/// Issues exactly one `read` per call rather than looping until a short read
+/// comes back: a frame whose length happens to be an exact multiple of
+/// `BUFFER_SIZE` would otherwise force one extra blocking `read` - stalling
+/// for the full read timeout - before it could be parsed and returned.
+pub fn read_messages(stream: &mut impl Read, buffer: &mut Vec<u8>) -> Result<Vec<DataType>, anyhow::Error> {
+    let mut chunk = [0u8; BUFFER_SIZE];
+    let read_bytes = read_next_bytes(stream, &mut chunk);
+    if read_bytes > 0 {
+        buffer.extend_from_slice(&chunk[0..read_bytes]);
+    }
+
+    let (messages, consumed) = protocol::read_messages_from_bytes(buffer)?;
+    buffer.drain(0..consumed);
+    Ok(messages)
+}
 
+/// Blocks until a single complete RESP frame has arrived on `stream`,
+/// reading as many times as it takes to reassemble it. Used by the
+/// replication handshake, where each step expects exactly one reply.
+/// Returns `None` if the stream is closed before a complete frame arrives.
+pub fn read_single_message(stream: &mut impl Read) -> Result<Option<DataType>, anyhow::Error> {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut chunk = [0u8; BUFFER_SIZE];
     loop {
-        let read_bytes = read_next_bytes(stream, &mut buffer);
-        if read_bytes > 0 {
-            total_read_bytes = total_read_bytes + read_bytes;
-            message_bytes.extend(&buffer[0..read_bytes]);
+        match DataType::try_parse(&buffer, 0)? {
+            DecodeOutcome::Complete((message, _)) => return Ok(Some(message)),
+            DecodeOutcome::Incomplete { .. } => (),
+        }
+        let read_bytes = read_next_bytes(stream, &mut chunk);
+        if read_bytes == 0 {
+            return Ok(None);
         }
-        if read_bytes < BUFFER_SIZE {
-            break;
+        buffer.extend_from_slice(&chunk[0..read_bytes]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use crate::commands;
+
+    /// A `Read` stream fed from a fixed script of byte chunks, one `read`
+    /// call draining exactly one scripted chunk (or returning `Ok(0)` once
+    /// the script is exhausted), so a test can control precisely where a
+    /// TCP-level read boundary falls - including mid-frame and mid-UTF-8.
+    struct MockStream {
+        chunks: VecDeque<Vec<u8>>,
+    }
+
+    impl MockStream {
+        fn new(chunks: Vec<Vec<u8>>) -> MockStream {
+            MockStream { chunks: chunks.into_iter().collect() }
+        }
+    }
+
+    impl Read for MockStream {
+        fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+            match self.chunks.pop_front() {
+                Some(chunk) => {
+                    buffer[0..chunk.len()].copy_from_slice(&chunk);
+                    Ok(chunk.len())
+                }
+                None => Ok(0),
+            }
+        }
+    }
+
+    fn command_names(messages: &[DataType]) -> Vec<String> {
+        messages.iter().map(|message| commands::parse_command_name(message).unwrap()).collect()
+    }
+
+    #[test]
+    fn should_reassemble_a_frame_split_mid_frame_across_reads() {
+        let ping = protocol::array(vec![protocol::bulk_string("PING")]).serialize();
+        let split_at = ping.len() / 2;
+        let mut stream = MockStream::new(vec![ping[0..split_at].to_vec(), ping[split_at..].to_vec()]);
+        let mut buffer = Vec::new();
+
+        assert_eq!(read_messages(&mut stream, &mut buffer).unwrap(), vec![]);
+        assert_eq!(command_names(&read_messages(&mut stream, &mut buffer).unwrap()), vec!["PING".to_owned()]);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn should_reassemble_a_bulk_string_split_mid_utf8_character() {
+        // "é" is the two-byte UTF-8 sequence 0xC3 0xA9; split the SET command
+        // right between those two bytes.
+        let set = protocol::array(vec![
+            protocol::bulk_string("SET"),
+            protocol::bulk_string("key"),
+            protocol::bulk_string("caf\u{e9}"),
+        ]).serialize();
+        let split_index = set.windows(2).position(|pair| pair == [0xC3, 0xA9]).unwrap() + 1;
+        let mut stream = MockStream::new(vec![set[0..split_index].to_vec(), set[split_index..].to_vec()]);
+        let mut buffer = Vec::new();
+
+        assert_eq!(read_messages(&mut stream, &mut buffer).unwrap(), vec![]);
+        let messages = read_messages(&mut stream, &mut buffer).unwrap();
+        assert_eq!(command_names(&messages), vec!["SET".to_owned()]);
+        assert_eq!(messages[0].as_vec().unwrap()[2], "caf\u{e9}");
+    }
+
+    #[test]
+    fn should_parse_several_pipelined_commands_delivered_in_one_chunk() {
+        let pipelined: Vec<u8> = [
+            protocol::array(vec![protocol::bulk_string("PING")]).serialize(),
+            protocol::array(vec![protocol::bulk_string("SET"), protocol::bulk_string("key"), protocol::bulk_string("value")]).serialize(),
+            protocol::array(vec![protocol::bulk_string("GET"), protocol::bulk_string("key")]).serialize(),
+        ].concat();
+        let mut stream = MockStream::new(vec![pipelined]);
+        let mut buffer = Vec::new();
+
+        let messages = read_messages(&mut stream, &mut buffer).unwrap();
+
+        assert_eq!(command_names(&messages), vec!["PING".to_owned(), "SET".to_owned(), "GET".to_owned()]);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn should_parse_a_frame_whose_length_is_an_exact_multiple_of_buffer_size_without_an_extra_read() {
+        // Pad a PING frame out to exactly BUFFER_SIZE bytes with a big bulk
+        // string, so a single `read` returns the entire frame and nothing
+        // else - this used to fool the old implementation into blocking for
+        // one more read before it would even attempt to parse.
+        let ping = protocol::array(vec![protocol::bulk_string("PING")]).serialize();
+        // Grow the padding value one byte at a time until the two frames
+        // together land on exactly BUFFER_SIZE - simpler than hand-deriving
+        // the RESP length-prefix overhead, and robust to it changing.
+        let mut padding_value = String::new();
+        let mut padding = protocol::array(vec![protocol::bulk_string(&padding_value)]).serialize();
+        while ping.len() + padding.len() < BUFFER_SIZE {
+            padding_value.push('x');
+            padding = protocol::array(vec![protocol::bulk_string(&padding_value)]).serialize();
         }
+        assert_eq!(ping.len() + padding.len(), BUFFER_SIZE);
+
+        let mut stream = MockStream::new(vec![[ping, padding].concat(), Vec::new()]);
+        let mut buffer = Vec::new();
+
+        let messages = read_messages(&mut stream, &mut buffer).unwrap();
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(command_names(&messages[0..1]), vec!["PING".to_owned()]);
+        assert_eq!(messages[1].as_vec().unwrap()[0], padding_value);
+        assert!(buffer.is_empty());
     }
-    if total_read_bytes == 0 {
-        Ok(None)
-    } else {
-        Ok(Some(message_bytes))
+
+    #[test]
+    fn should_retain_a_trailing_partial_command_for_the_next_read() {
+        let ping = protocol::array(vec![protocol::bulk_string("PING")]).serialize();
+        let get = protocol::array(vec![protocol::bulk_string("GET"), protocol::bulk_string("key")]).serialize();
+        let split_at = get.len() / 2;
+        let mut stream = MockStream::new(vec![[ping.clone(), get[0..split_at].to_vec()].concat(), get[split_at..].to_vec()]);
+        let mut buffer = Vec::new();
+
+        let first_batch = read_messages(&mut stream, &mut buffer).unwrap();
+        assert_eq!(command_names(&first_batch), vec!["PING".to_owned()]);
+        assert!(!buffer.is_empty());
+
+        let second_batch = read_messages(&mut stream, &mut buffer).unwrap();
+        assert_eq!(command_names(&second_batch), vec!["GET".to_owned()]);
+        assert!(buffer.is_empty());
     }
-}
\ No newline at end of file
+}
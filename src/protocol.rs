@@ -1,20 +1,84 @@
+use std::fmt;
 use anyhow::{anyhow, ensure, Context};
 
 use crate::error::RedisError;
 
-pub fn read_messages_from_bytes(message_bytes: &Vec<u8>) -> Result<Vec<DataType>, anyhow::Error> {
+/// Signals that `DataType::parse` ran out of bytes before it could finish
+/// reading a frame, rather than that the bytes it did see were malformed.
+///
+/// Callers that read from a socket (see `io::read_messages`) treat this as
+/// "come back once more bytes have arrived" instead of a hard parse
+/// failure, so a frame split across TCP reads is reassembled instead of
+/// erroring out or panicking on an out-of-range slice.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Incomplete;
+
+impl std::error::Error for Incomplete {}
+
+impl fmt::Display for Incomplete {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Incomplete: not enough bytes to parse a complete RESP frame yet")
+    }
+}
+
+pub fn is_incomplete(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<Incomplete>().is_some()
+}
+
+/// Outcome of a single `DataType::try_parse` call: either a full frame plus
+/// the position right after it, or a signal that `input` was truncated.
+///
+/// This is the same information `parse`/`is_incomplete` already carry
+/// through an `anyhow::Error`, surfaced as a plain enum for callers that
+/// would rather match on a result than downcast an error on every read.
+#[derive(Debug, PartialEq, Clone)]
+pub enum DecodeOutcome {
+    Complete((DataType, usize)),
+    Incomplete {
+        /// Lower bound on how many more bytes `input` needs before another
+        /// attempt can succeed. Parsing has not looked far enough ahead to
+        /// know the true requirement (e.g. a length header hasn't arrived
+        /// yet), so this is only ever a floor, never an exact count.
+        needed_at_least: usize
+    }
+}
+
+impl DataType {
+    /// Like `parse`, but reports a truncated buffer as `DecodeOutcome::Incomplete`
+    /// instead of propagating it as an error, so a connection loop that reads
+    /// off a socket can match on the outcome instead of downcasting.
+    pub fn try_parse(input: &Vec<u8>, position: usize) -> Result<DecodeOutcome, anyhow::Error> {
+        match DataType::parse(input, position) {
+            Ok((value, new_position)) => Ok(DecodeOutcome::Complete((value, new_position))),
+            Err(error) if is_incomplete(&error) => Ok(DecodeOutcome::Incomplete {
+                needed_at_least: (position + 1).saturating_sub(input.len()).max(1)
+            }),
+            Err(error) => Err(error)
+        }
+    }
+}
+
+/// Parses as many complete frames out of `message_bytes` as it can, returning
+/// them along with how many bytes they consumed. Any leftover bytes after the
+/// last complete frame are left unconsumed (reported via the returned count)
+/// rather than erroring, so a caller holding onto the buffer can keep them
+/// around and resume once more bytes arrive.
+pub fn read_messages_from_bytes(message_bytes: &Vec<u8>) -> Result<(Vec<DataType>, usize), anyhow::Error> {
     let mut messages: Vec<DataType> = Vec::new();
     let mut current_position = 0;
-    let total_length = message_bytes.len();
 
-    while current_position < total_length {
-        let (parsed, new_position) = DataType::parse(&message_bytes, current_position)?;
-        current_position = new_position;
-        messages.push(parsed);
+    loop {
+        match DataType::try_parse(message_bytes, current_position)? {
+            DecodeOutcome::Complete((parsed, new_position)) => {
+                current_position = new_position;
+                messages.push(parsed);
+            },
+            DecodeOutcome::Incomplete { .. } => break
+        }
     }
     println!("Read messages bytes {:?}", message_bytes);
     println!("Parsed them as messages {:?}", messages);
-    Ok(messages)
+    Ok((messages, current_position))
 }
 
 //TODO #2: This might return multiple messages at one time, messages are not necessarily received one by one
@@ -35,11 +99,11 @@ pub fn read_message_from_bytes(message_bytes: &Vec<u8>) -> Result<DataType, anyh
     }
 }
 
-fn read_and_assert_symbol(input: &Vec<u8>, symbol: u8, position: usize) -> Result<usize, anyhow::Error> {
+fn read_and_assert_symbol(input: &[u8], symbol: u8, position: usize) -> Result<usize, anyhow::Error> {
     let error_message = format!("Expected symbol '{}' in '{}' at position {}", symbol as char, String::from_utf8_lossy(&input.clone()), position);
-    let &actual_symbol = input.get(position).ok_or::<anyhow::Error>(RedisError {
-        message: error_message.clone()
-    }.into())?;
+    // A missing byte just means the frame hasn't fully arrived yet, not that
+    // it's malformed, so it is reported as `Incomplete` rather than a hard error.
+    let &actual_symbol = input.get(position).ok_or::<anyhow::Error>(Incomplete.into())?;
     if actual_symbol != symbol {
         Err(RedisError {
             message: error_message
@@ -57,7 +121,11 @@ fn maybe_slice_of<T>(vec: &[T], start: usize, end: usize) -> Option<&[T]> {
     }
 }
 
-fn find_position_before_terminator(input: &Vec<u8>, terminator: &Vec<u8>, position: usize) -> usize {
+/// Looks for `terminator` starting at `position`, returning the index right
+/// before it if the whole terminator is present in `input`. Returns `None`
+/// when `input` runs out first, which callers treat as "the header hasn't
+/// fully arrived yet" (`Incomplete`) rather than "there is no terminator".
+fn find_position_before_terminator(input: &[u8], terminator: &[u8], position: usize) -> Option<usize> {
     let mut current = position;
     let mut end_index: Option<usize> = None;
     while end_index == None && current < input.len() {
@@ -72,11 +140,11 @@ fn find_position_before_terminator(input: &Vec<u8>, terminator: &Vec<u8>, positi
             current = current + 1
         }
     }
-    if let Some(new_position) = end_index {
-        new_position
-    } else {
-        current
-    }
+    end_index
+}
+
+fn find_crlf_or_incomplete(input: &[u8], position: usize) -> Result<usize, anyhow::Error> {
+    find_position_before_terminator(input, "\r\n".as_bytes(), position).ok_or_else(|| Incomplete.into())
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -128,6 +196,15 @@ pub enum DataType {
     }
 }
 
+/// Wire-protocol version a client negotiated via `HELLO`. Determines whether
+/// `DataType::serialize_as` can emit RESP3-only frames as-is or must lower
+/// them to their closest RESP2 equivalent first.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RespVersion {
+    Resp2,
+    Resp3
+}
+
 pub fn double(value: f64) -> DataType {
     DataType::Double {
         value
@@ -162,6 +239,10 @@ pub fn array(elements: Vec<DataType>) -> DataType {
     DataType::Array { elements }
 }
 
+pub fn push(elements: Vec<DataType>) -> DataType {
+    DataType::Push { elements }
+}
+
 //TODO: Implement the rest of the constructors
 /*
     BigNumber {
@@ -236,6 +317,12 @@ pub fn boolean(value: bool) -> DataType {
 
 impl DataType {
 
+    /// Alias for `as_array`, used by callers that think of a parsed command
+    /// as a vector of its string arguments.
+    pub fn as_vec(&self) -> Result<Vec<String>, anyhow::Error> {
+        self.as_array()
+    }
+
     pub fn as_array(&self) -> Result<Vec<String>, anyhow::Error> {
         match &self {
             &DataType::Array { elements } => {
@@ -251,6 +338,34 @@ impl DataType {
         }
     }
 
+    /// Like `as_array`, but returns the raw bytes of each argument instead of
+    /// requiring them to be valid UTF-8. Commands that need to round-trip
+    /// opaque blobs (SET/GET/ECHO) parse through this instead of `as_array`,
+    /// so only the command name itself ever needs lossy decoding.
+    pub fn as_byte_array(&self) -> Result<Vec<Vec<u8>>, anyhow::Error> {
+        match &self {
+            &DataType::Array { elements } => {
+                elements.iter().map(|element| element.as_bytes_lossless()).collect()
+            },
+            _ => {
+                Ok(vec![self.as_bytes_lossless()?])
+            }
+        }
+    }
+
+    /// Raw bytes of a single value. Unlike `as_string`, this never fails due
+    /// to invalid UTF-8 for the types a binary-safe command argument can
+    /// actually be (`BulkString`/`SimpleString`/`Rdb`); other types fall back
+    /// to their textual representation.
+    fn as_bytes_lossless(&self) -> Result<Vec<u8>, anyhow::Error> {
+        match &self {
+            &DataType::BulkString { value } => Ok(value.clone().unwrap_or_default()),
+            &DataType::SimpleString { value } => Ok(value.clone()),
+            &DataType::Rdb { value } => Ok(value.clone()),
+            _ => Ok(self.as_string()?.into_bytes())
+        }
+    }
+
     pub fn as_string(&self) -> Result<String, anyhow::Error> {
         let mut result: Vec<u8> = Vec::new();
         match &self {
@@ -435,6 +550,64 @@ impl DataType {
         result
     }
 
+    /// Serializes `self` for a client that negotiated `version` via `HELLO`.
+    ///
+    /// `Resp3` is just `serialize()`. `Resp2` lowers every RESP3-only variant
+    /// to its closest RESP2 equivalent first (flattening `Map`/`Set` to
+    /// `*`-arrays, `Boolean` to `:1`/`:0`, `Double`/`BigNumber` to bulk
+    /// strings, `Null` to `$-1\r\n`, `VerbatimString` to a plain bulk string,
+    /// `Push` to a `*`-array, and `BulkError` to a `SimpleError`), so a
+    /// server can build one `DataType` tree and hand it to clients of
+    /// either generation.
+    pub fn serialize_as(&self, version: RespVersion) -> Vec<u8> {
+        if version == RespVersion::Resp3 {
+            return self.serialize();
+        }
+        match &self {
+            &DataType::Map { entries } => {
+                let mut elements: Vec<DataType> = Vec::new();
+                for (key, value) in entries.iter() {
+                    elements.push(key.clone());
+                    elements.push(value.clone());
+                }
+                serialize_array_like_as(&elements, b'*', version)
+            },
+            &DataType::Set { elements } => {
+                serialize_array_like_as(elements, b'*', version)
+            },
+            &DataType::Push { elements } => {
+                serialize_array_like_as(elements, b'*', version)
+            },
+            &DataType::Array { elements } => {
+                serialize_array_like_as(elements, b'*', version)
+            },
+            &DataType::Boolean { value } => {
+                DataType::Integer { value: if *value { 1 } else { 0 } }.serialize()
+            },
+            &DataType::Double { value } => {
+                bulk_string(&value.to_string()).serialize()
+            },
+            &DataType::BigNumber { sign, value } => {
+                let mut bytes: Vec<u8> = Vec::new();
+                if sign == &b'-' {
+                    bytes.push(*sign);
+                }
+                bytes.extend(value);
+                bulk_string_from_bytes(bytes).serialize()
+            },
+            &DataType::Null => {
+                DataType::BulkString { value: None }.serialize()
+            },
+            &DataType::VerbatimString { encoding: _, value } => {
+                bulk_string_from_bytes(value.clone()).serialize()
+            },
+            &DataType::BulkError { value } => {
+                DataType::SimpleError { value: value.clone() }.serialize()
+            },
+            _ => self.serialize()
+        }
+    }
+
     pub(crate) fn parse(input: &Vec<u8>, position: usize) -> Result<(DataType, usize), anyhow::Error> {
         if let Some(prefix_symbol) = input.get(position) {
             match prefix_symbol {
@@ -490,7 +663,9 @@ impl DataType {
                     }.into())
             }
         } else {
-            Err(RedisError { message: format!("Could not read the next data type value '{}' at position {}", String::from_utf8_lossy(&input.clone()), position) }.into())
+            // No prefix byte at this position yet: the frame hasn't arrived
+            // at all rather than being malformed.
+            Err(Incomplete.into())
         }
     }
 }
@@ -499,7 +674,7 @@ fn parse_double(input: &Vec<u8>, position: usize) -> Result<(DataType, usize), a
     let error_message = format!("Invalid Double '{}'", String::from_utf8_lossy(&input.clone()));
     read_and_assert_symbol(input, b',', position).context(error_message.clone())?;
     let value_start = position + 1;
-    let value_end = find_position_before_terminator(input, &"\r\n".as_bytes().to_vec(), value_start);
+    let value_end = find_crlf_or_incomplete(input, value_start)?;
     read_and_assert_symbol(input, b'\r', value_end).context(error_message.clone())?;
     read_and_assert_symbol(input, b'\n', value_end + 1).context(error_message.clone())?;
     let value: f64 = String::from_utf8(input[value_start..value_end].to_vec())?.parse()?;
@@ -512,15 +687,13 @@ fn parse_big_number(input: &Vec<u8>, position: usize) -> Result<(DataType, usize
     let error_message = format!("Invalid BigNumber '{}'", String::from_utf8_lossy(&input.clone()));
     read_and_assert_symbol(input, b'(', position).context(error_message.clone())?;
     let mut value_start = position + 1;
-    let &maybe_sign = input.get(position + 1).ok_or::<anyhow::Error>(RedisError {
-        message: error_message.clone()
-    }.into())?;
+    let &maybe_sign = input.get(position + 1).ok_or::<anyhow::Error>(Incomplete.into())?;
     let mut sign: Option<u8> = None;
     if maybe_sign == b'+' || maybe_sign == b'-' {
         value_start = position + 2;
         sign = Some(maybe_sign);
     }
-    let value_end = find_position_before_terminator(input, &"\r\n".as_bytes().to_vec(), value_start);
+    let value_end = find_crlf_or_incomplete(input, value_start)?;
     read_and_assert_symbol(input, b'\r', value_end).context(error_message.clone())?;
     read_and_assert_symbol(input, b'\n', value_end + 1).context(error_message.clone())?;
     Ok((DataType::BigNumber {
@@ -533,7 +706,7 @@ fn parse_integer(input: &Vec<u8>, position: usize) -> Result<(DataType, usize),
     let error_message = format!("Invalid Integer '{}'", String::from_utf8_lossy(&input.clone()));
     read_and_assert_symbol(input, b':', position).context(error_message.clone())?;
     let value_start = position + 1;
-    let value_end = find_position_before_terminator(input, &"\r\n".as_bytes().to_vec(), value_start);
+    let value_end = find_crlf_or_incomplete(input, value_start)?;
     read_and_assert_symbol(input, b'\r', value_end).context(error_message.clone())?;
     read_and_assert_symbol(input, b'\n', value_end + 1).context(error_message.clone())?;
     Ok((DataType::Integer {
@@ -545,7 +718,7 @@ fn parse_simple_error(input: &Vec<u8>, position: usize) -> Result<(DataType, usi
     let error_message = format!("Invalid SimpleError '{}'", String::from_utf8_lossy(&input.clone()));
     read_and_assert_symbol(input, b'-', position).context(error_message.clone())?;
     let value_start = position + 1;
-    let value_end = find_position_before_terminator(input, &"\r\n".as_bytes().to_vec(), value_start);
+    let value_end = find_crlf_or_incomplete(input, value_start)?;
     read_and_assert_symbol(input, b'\r', value_end).context(error_message.clone())?;
     read_and_assert_symbol(input, b'\n', value_end + 1).context(error_message.clone())?;
     Ok((DataType::SimpleError {
@@ -562,13 +735,20 @@ fn parse_bulk_string_or_rdb(input: &Vec<u8>, position: usize) -> Result<(DataTyp
 
     let mut new_position = position ;
     if first_length_symbol != Some(&b'-') {
-        let length_end = find_position_before_terminator(input, &"\r\n".as_bytes().to_vec(), length_start);
+        let length_end = find_crlf_or_incomplete(input, length_start)?;
         let string_length: usize = String::from_utf8_lossy(&input[length_start..length_end]).parse()?;
         read_and_assert_symbol(input, b'\r', length_end).context(error_message.clone())?;
         read_and_assert_symbol(input, b'\n', length_end + 1).context(error_message.clone())?;
         let value_start = length_end + 2;
         let value_end = length_end + 2 + string_length;
 
+        // The bulk string's content bytes may not have arrived yet even
+        // though its length header has; wait for the rest rather than
+        // slicing past the end of what has been read so far.
+        if value_end > input.len() {
+            return Err(Incomplete.into());
+        }
+
         let maybe_bulk_string_end = maybe_slice_of(input, value_end, value_end + 2);
         if maybe_bulk_string_end == Some("\r\n".as_bytes()) {
             new_position = value_end + 2;
@@ -581,7 +761,16 @@ fn parse_bulk_string_or_rdb(input: &Vec<u8>, position: usize) -> Result<(DataTyp
             }, value_end))
         }
     } else {
-        new_position = new_position + "$-1\r\n".len();
+        let null_bulk_string = "$-1\r\n".as_bytes();
+        if input.len() < position + null_bulk_string.len() {
+            return Err(Incomplete.into());
+        }
+        if &input[position..position + null_bulk_string.len()] != null_bulk_string {
+            return Err(RedisError {
+                message: error_message.clone()
+            }.into());
+        }
+        new_position = new_position + null_bulk_string.len();
         Ok((DataType::BulkString {
             value: None
         }, new_position))
@@ -592,7 +781,7 @@ fn parse_bulk_error(input: &Vec<u8>, position: usize) -> Result<(DataType, usize
     let error_message = format!("Invalid BulkString '{}'", String::from_utf8_lossy(&input.clone()));
     read_and_assert_symbol(input, b'!', position).context(error_message.clone())?;
     let length_start = position + 1;
-    let length_end = find_position_before_terminator(input, &"\r\n".as_bytes().to_vec(), length_start);
+    let length_end = find_crlf_or_incomplete(input, length_start)?;
     let content_length: usize = String::from_utf8_lossy(&input[length_start..length_end]).parse()?;
     read_and_assert_symbol(input, b'\r', length_end).context(error_message.clone())?;
     read_and_assert_symbol(input, b'\n', length_end + 1).context(error_message.clone())?;
@@ -609,7 +798,7 @@ fn parse_verbatim_string(input: &Vec<u8>, position: usize) -> Result<(DataType,
     let error_message = format!("Invalid VerbatimString '{}'", String::from_utf8_lossy(&input.clone()));
     read_and_assert_symbol(input, b'=', position).context(error_message.clone())?;
     let length_start = position + 1;
-    let length_end = find_position_before_terminator(input, &"\r\n".as_bytes().to_vec(), length_start);
+    let length_end = find_crlf_or_incomplete(input, length_start)?;
     let content_length: usize = String::from_utf8_lossy(&input[length_start..length_end]).parse()?;
     read_and_assert_symbol(input, b'\r', length_end).context(error_message.clone())?;
     read_and_assert_symbol(input, b'\n', length_end + 1).context(error_message.clone())?;
@@ -631,7 +820,7 @@ fn parse_simple_string(input: &Vec<u8>, position: usize) -> Result<(DataType, us
     let error_message = format!("Invalid SimpleString '{}'", String::from_utf8_lossy(&input.clone()));
     read_and_assert_symbol(input, b'+', position).context(error_message.clone())?;
     let value_start = position + 1;
-    let value_end = find_position_before_terminator(input, &"\r\n".as_bytes().to_vec(), value_start);
+    let value_end = find_crlf_or_incomplete(input, value_start)?;
     read_and_assert_symbol(input, b'\r', value_end).context(error_message.clone())?;
     read_and_assert_symbol(input, b'\n', value_end + 1).context(error_message.clone())?;
     Ok((DataType::SimpleString {
@@ -643,7 +832,7 @@ fn parse_map(input: &Vec<u8>, position: usize) -> Result<(DataType, usize), anyh
     let error_message = format!("Invalid Map '{}'", String::from_utf8_lossy(&input.clone()));
     read_and_assert_symbol(input, b'%', position).context(error_message.clone())?;
     let length_start = position + 1;
-    let length_end = find_position_before_terminator(input, &"\r\n".as_bytes().to_vec(), length_start);
+    let length_end = find_crlf_or_incomplete(input, length_start)?;
     let map_length: i64 = String::from_utf8_lossy(&input[length_start..length_end]).parse()?;
     read_and_assert_symbol(input, b'\r', length_end).context(error_message.clone())?;
     read_and_assert_symbol(input, b'\n', length_end + 1).context(error_message.clone())?;
@@ -666,7 +855,7 @@ fn parse_set(input: &Vec<u8>, position: usize) -> Result<(DataType, usize), anyh
     let error_message = format!("Invalid Set '{}'", String::from_utf8_lossy(&input.clone()));
     read_and_assert_symbol(input, b'~', position).context(error_message.clone())?;
     let length_start = position + 1;
-    let length_end = find_position_before_terminator(input, &"\r\n".as_bytes().to_vec(), length_start);
+    let length_end = find_crlf_or_incomplete(input, length_start)?;
     let map_length: i64 = String::from_utf8_lossy(&input[length_start..length_end]).parse()?;
     read_and_assert_symbol(input, b'\r', length_end).context(error_message.clone())?;
     read_and_assert_symbol(input, b'\n', length_end + 1).context(error_message.clone())?;
@@ -695,11 +884,22 @@ fn serialize_array_like(elements: &Vec<DataType>, prefix: u8) -> Vec<u8> {
     result
 }
 
+fn serialize_array_like_as(elements: &Vec<DataType>, prefix: u8, version: RespVersion) -> Vec<u8> {
+    let mut result: Vec<u8> = Vec::new();
+    result.push(prefix);
+    result.extend(elements.len().to_string().as_bytes());
+    result.extend("\r\n".as_bytes());
+    for element in elements.iter() {
+        result.extend(element.serialize_as(version));
+    }
+    result
+}
+
 fn parse_array_like(input: &Vec<u8>, position: usize, prefix: u8) -> Result<(Vec<DataType>, usize), anyhow::Error> {
     let error_message = format!("Invalid Array-like '{}'", String::from_utf8_lossy(&input.clone()));
     read_and_assert_symbol(input, prefix, position).context(error_message.clone())?;
     let length_start = position + 1;
-    let length_end = find_position_before_terminator(input, &"\r\n".as_bytes().to_vec(), length_start);
+    let length_end = find_crlf_or_incomplete(input, length_start)?;
     let array_length: i64 = String::from_utf8_lossy(&input[length_start..length_end]).parse()?;
     read_and_assert_symbol(input, b'\r', length_end).context(error_message.clone())?;
     read_and_assert_symbol(input, b'\n', length_end + 1).context(error_message.clone())?;
@@ -747,6 +947,350 @@ fn parse_boolean(input: &Vec<u8>, position: usize) -> Result<(DataType, usize),
     Ok((DataType::Boolean { value }, position + 4))
 }
 
+/// Borrowed, zero-copy counterpart to `DataType`.
+///
+/// `DataType::parse` copies every string-like value out of `input` as soon
+/// as it recognizes it (`.to_vec()`), and every error path formats the
+/// *entire* input buffer via `input.clone()` even on the common "not
+/// malformed, just Incomplete" path. For a large multi-bulk request both of
+/// those copy the whole buffer on every parse call. `DataTypeRef::parse`
+/// follows the same RESP grammar as `DataType::parse`, but every
+/// string-like variant borrows a `&'de [u8]` slice of `input` instead of
+/// owning a `Vec<u8>`, and error context is built lazily from just the
+/// offending offset so it costs nothing unless parsing actually fails.
+/// Call `.to_owned()` to convert a parsed value into the owning `DataType`
+/// once its lifetime needs to outlive `input`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum DataTypeRef<'de> {
+    Double {
+        value: f64
+    },
+    BigNumber {
+        sign: u8,
+        value: &'de [u8]
+    },
+    Integer {
+        value: i64
+    },
+    SimpleError {
+        value: &'de [u8]
+    },
+    BulkString {
+        value: Option<&'de [u8]>
+    },
+    Rdb {
+        value: &'de [u8]
+    },
+    BulkError {
+        value: &'de [u8]
+    },
+    VerbatimString {
+        encoding: &'de [u8],
+        value: &'de [u8]
+    },
+    SimpleString {
+        value: &'de [u8]
+    },
+    Map {
+        entries: Vec<(DataTypeRef<'de>, DataTypeRef<'de>)>
+    },
+    Set {
+        elements: Vec<DataTypeRef<'de>>
+    },
+    Array {
+        elements: Vec<DataTypeRef<'de>>
+    },
+    Push {
+        elements: Vec<DataTypeRef<'de>>
+    },
+    Null,
+    Boolean {
+        value: bool
+    }
+}
+
+impl<'de> DataTypeRef<'de> {
+    /// Converts this borrowed value into the owning `DataType`, copying
+    /// every slice it holds. Needed once a parsed value must outlive the
+    /// buffer it was parsed from, e.g. to stash it past the current read.
+    pub fn to_owned(&self) -> DataType {
+        match self {
+            DataTypeRef::Double { value } => DataType::Double { value: *value },
+            DataTypeRef::BigNumber { sign, value } => DataType::BigNumber { sign: *sign, value: value.to_vec() },
+            DataTypeRef::Integer { value } => DataType::Integer { value: *value },
+            DataTypeRef::SimpleError { value } => DataType::SimpleError { value: value.to_vec() },
+            DataTypeRef::BulkString { value } => DataType::BulkString { value: value.map(|value| value.to_vec()) },
+            DataTypeRef::Rdb { value } => DataType::Rdb { value: value.to_vec() },
+            DataTypeRef::BulkError { value } => DataType::BulkError { value: value.to_vec() },
+            DataTypeRef::VerbatimString { encoding, value } => DataType::VerbatimString { encoding: encoding.to_vec(), value: value.to_vec() },
+            DataTypeRef::SimpleString { value } => DataType::SimpleString { value: value.to_vec() },
+            DataTypeRef::Map { entries } => DataType::Map {
+                entries: entries.iter().map(|(key, value)| (key.to_owned(), value.to_owned())).collect()
+            },
+            DataTypeRef::Set { elements } => DataType::Set { elements: elements.iter().map(DataTypeRef::to_owned).collect() },
+            DataTypeRef::Array { elements } => DataType::Array { elements: elements.iter().map(DataTypeRef::to_owned).collect() },
+            DataTypeRef::Push { elements } => DataType::Push { elements: elements.iter().map(DataTypeRef::to_owned).collect() },
+            DataTypeRef::Null => DataType::Null,
+            DataTypeRef::Boolean { value } => DataType::Boolean { value: *value }
+        }
+    }
+
+    pub(crate) fn parse(input: &'de [u8], position: usize) -> Result<(DataTypeRef<'de>, usize), anyhow::Error> {
+        if let Some(prefix_symbol) = input.get(position) {
+            match prefix_symbol {
+                b',' => parse_ref_double(input, position),
+                b'(' => parse_ref_big_number(input, position),
+                b':' => parse_ref_integer(input, position),
+                b'-' => parse_ref_simple_error(input, position),
+                b'$' => parse_ref_bulk_string_or_rdb(input, position),
+                b'!' => parse_ref_bulk_error(input, position),
+                b'=' => parse_ref_verbatim_string(input, position),
+                b'+' => parse_ref_simple_string(input, position),
+                b'%' => parse_ref_map(input, position),
+                b'~' => parse_ref_set(input, position),
+                b'*' => parse_ref_array(input, position),
+                b'>' => parse_ref_push(input, position),
+                b'_' => parse_ref_null(input, position),
+                b'#' => parse_ref_boolean(input, position),
+                ch => {
+                    let ch = *ch;
+                    Err(RedisError {
+                        message: format!("Could not read the next data type value at position {}, unsupported prefix '{}'",
+                            position,
+                            String::from_utf8_lossy(&[ch])
+                        )
+                    }.into())
+                }
+            }
+        } else {
+            Err(Incomplete.into())
+        }
+    }
+}
+
+fn parse_ref_double(input: &[u8], position: usize) -> Result<(DataTypeRef<'_>, usize), anyhow::Error> {
+    read_and_assert_symbol(input, b',', position).with_context(|| format!("Invalid Double at position {}", position))?;
+    let value_start = position + 1;
+    let value_end = find_crlf_or_incomplete(input, value_start)?;
+    read_and_assert_symbol(input, b'\r', value_end).with_context(|| format!("Invalid Double at position {}", position))?;
+    read_and_assert_symbol(input, b'\n', value_end + 1).with_context(|| format!("Invalid Double at position {}", position))?;
+    let value: f64 = std::str::from_utf8(&input[value_start..value_end])?.parse()?;
+    Ok((DataTypeRef::Double { value }, value_end + 2))
+}
+
+fn parse_ref_big_number(input: &[u8], position: usize) -> Result<(DataTypeRef<'_>, usize), anyhow::Error> {
+    read_and_assert_symbol(input, b'(', position).with_context(|| format!("Invalid BigNumber at position {}", position))?;
+    let mut value_start = position + 1;
+    let &maybe_sign = input.get(position + 1).ok_or::<anyhow::Error>(Incomplete.into())?;
+    let mut sign: Option<u8> = None;
+    if maybe_sign == b'+' || maybe_sign == b'-' {
+        value_start = position + 2;
+        sign = Some(maybe_sign);
+    }
+    let value_end = find_crlf_or_incomplete(input, value_start)?;
+    read_and_assert_symbol(input, b'\r', value_end).with_context(|| format!("Invalid BigNumber at position {}", position))?;
+    read_and_assert_symbol(input, b'\n', value_end + 1).with_context(|| format!("Invalid BigNumber at position {}", position))?;
+    Ok((DataTypeRef::BigNumber {
+        sign: sign.unwrap_or(b'+'),
+        value: &input[value_start..value_end]
+    }, value_end + 2))
+}
+
+fn parse_ref_integer(input: &[u8], position: usize) -> Result<(DataTypeRef<'_>, usize), anyhow::Error> {
+    read_and_assert_symbol(input, b':', position).with_context(|| format!("Invalid Integer at position {}", position))?;
+    let value_start = position + 1;
+    let value_end = find_crlf_or_incomplete(input, value_start)?;
+    read_and_assert_symbol(input, b'\r', value_end).with_context(|| format!("Invalid Integer at position {}", position))?;
+    read_and_assert_symbol(input, b'\n', value_end + 1).with_context(|| format!("Invalid Integer at position {}", position))?;
+    Ok((DataTypeRef::Integer {
+        value: std::str::from_utf8(&input[value_start..value_end])?.parse()?
+    }, value_end + 2))
+}
+
+fn parse_ref_simple_error(input: &[u8], position: usize) -> Result<(DataTypeRef<'_>, usize), anyhow::Error> {
+    read_and_assert_symbol(input, b'-', position).with_context(|| format!("Invalid SimpleError at position {}", position))?;
+    let value_start = position + 1;
+    let value_end = find_crlf_or_incomplete(input, value_start)?;
+    read_and_assert_symbol(input, b'\r', value_end).with_context(|| format!("Invalid SimpleError at position {}", position))?;
+    read_and_assert_symbol(input, b'\n', value_end + 1).with_context(|| format!("Invalid SimpleError at position {}", position))?;
+    Ok((DataTypeRef::SimpleError {
+        value: &input[value_start..value_end]
+    }, value_end + 2))
+}
+
+fn parse_ref_bulk_string_or_rdb(input: &[u8], position: usize) -> Result<(DataTypeRef<'_>, usize), anyhow::Error> {
+    read_and_assert_symbol(input, b'$', position).with_context(|| format!("Invalid BulkString at position {}", position))?;
+    let length_start = position + 1;
+    let first_length_symbol = input.get(length_start);
+
+    if first_length_symbol != Some(&b'-') {
+        let length_end = find_crlf_or_incomplete(input, length_start)?;
+        let string_length: usize = String::from_utf8_lossy(&input[length_start..length_end]).parse()?;
+        read_and_assert_symbol(input, b'\r', length_end).with_context(|| format!("Invalid BulkString at position {}", position))?;
+        read_and_assert_symbol(input, b'\n', length_end + 1).with_context(|| format!("Invalid BulkString at position {}", position))?;
+        let value_start = length_end + 2;
+        let value_end = length_end + 2 + string_length;
+
+        if value_end > input.len() {
+            return Err(Incomplete.into());
+        }
+
+        let maybe_bulk_string_end = maybe_slice_of(input, value_end, value_end + 2);
+        if maybe_bulk_string_end == Some("\r\n".as_bytes()) {
+            Ok((DataTypeRef::BulkString {
+                value: Some(&input[value_start..value_end])
+            }, value_end + 2))
+        } else {
+            Ok((DataTypeRef::Rdb {
+                value: &input[value_start..value_end]
+            }, value_end))
+        }
+    } else {
+        let null_bulk_string = "$-1\r\n".as_bytes();
+        if input.len() < position + null_bulk_string.len() {
+            return Err(Incomplete.into());
+        }
+        if &input[position..position + null_bulk_string.len()] != null_bulk_string {
+            return Err(RedisError {
+                message: format!("Invalid BulkString at position {}", position)
+            }.into());
+        }
+        Ok((DataTypeRef::BulkString { value: None }, position + null_bulk_string.len()))
+    }
+}
+
+fn parse_ref_bulk_error(input: &[u8], position: usize) -> Result<(DataTypeRef<'_>, usize), anyhow::Error> {
+    read_and_assert_symbol(input, b'!', position).with_context(|| format!("Invalid BulkError at position {}", position))?;
+    let length_start = position + 1;
+    let length_end = find_crlf_or_incomplete(input, length_start)?;
+    let content_length: usize = String::from_utf8_lossy(&input[length_start..length_end]).parse()?;
+    read_and_assert_symbol(input, b'\r', length_end).with_context(|| format!("Invalid BulkError at position {}", position))?;
+    read_and_assert_symbol(input, b'\n', length_end + 1).with_context(|| format!("Invalid BulkError at position {}", position))?;
+    let value_start = length_end + 2;
+    let value_end = length_end + 2 + content_length;
+    read_and_assert_symbol(input, b'\r', value_end).with_context(|| format!("Invalid BulkError at position {}", position))?;
+    read_and_assert_symbol(input, b'\n', value_end + 1).with_context(|| format!("Invalid BulkError at position {}", position))?;
+    Ok((DataTypeRef::BulkError {
+        value: &input[value_start..value_end]
+    }, value_end + 2))
+}
+
+fn parse_ref_verbatim_string(input: &[u8], position: usize) -> Result<(DataTypeRef<'_>, usize), anyhow::Error> {
+    read_and_assert_symbol(input, b'=', position).with_context(|| format!("Invalid VerbatimString at position {}", position))?;
+    let length_start = position + 1;
+    let length_end = find_crlf_or_incomplete(input, length_start)?;
+    let content_length: usize = String::from_utf8_lossy(&input[length_start..length_end]).parse()?;
+    read_and_assert_symbol(input, b'\r', length_end).with_context(|| format!("Invalid VerbatimString at position {}", position))?;
+    read_and_assert_symbol(input, b'\n', length_end + 1).with_context(|| format!("Invalid VerbatimString at position {}", position))?;
+    let value_start = length_end + 2;
+    let value_end = length_end + 2 + content_length;
+    read_and_assert_symbol(input, b'\r', value_end).with_context(|| format!("Invalid VerbatimString at position {}", position))?;
+    read_and_assert_symbol(input, b'\n', value_end + 1).with_context(|| format!("Invalid VerbatimString at position {}", position))?;
+    let encoding_and_content = &input[value_start..value_end];
+    let index_before_content = encoding_and_content.iter().position(|&ch| ch == b':').ok_or(RedisError {
+        message: format!("Invalid VerbatimString at position {}", position)
+    })?;
+    Ok((DataTypeRef::VerbatimString {
+        encoding: &input[value_start..(value_start + index_before_content)],
+        value: &input[(value_start + index_before_content + 1)..value_end]
+    }, value_end + 2))
+}
+
+fn parse_ref_simple_string(input: &[u8], position: usize) -> Result<(DataTypeRef<'_>, usize), anyhow::Error> {
+    read_and_assert_symbol(input, b'+', position).with_context(|| format!("Invalid SimpleString at position {}", position))?;
+    let value_start = position + 1;
+    let value_end = find_crlf_or_incomplete(input, value_start)?;
+    read_and_assert_symbol(input, b'\r', value_end).with_context(|| format!("Invalid SimpleString at position {}", position))?;
+    read_and_assert_symbol(input, b'\n', value_end + 1).with_context(|| format!("Invalid SimpleString at position {}", position))?;
+    Ok((DataTypeRef::SimpleString {
+        value: &input[value_start..value_end]
+    }, value_end + 2))
+}
+
+fn parse_ref_map(input: &[u8], position: usize) -> Result<(DataTypeRef<'_>, usize), anyhow::Error> {
+    read_and_assert_symbol(input, b'%', position).with_context(|| format!("Invalid Map at position {}", position))?;
+    let length_start = position + 1;
+    let length_end = find_crlf_or_incomplete(input, length_start)?;
+    let map_length: i64 = String::from_utf8_lossy(&input[length_start..length_end]).parse()?;
+    read_and_assert_symbol(input, b'\r', length_end).with_context(|| format!("Invalid Map at position {}", position))?;
+    read_and_assert_symbol(input, b'\n', length_end + 1).with_context(|| format!("Invalid Map at position {}", position))?;
+    let mut entries = Vec::new();
+    let mut read_entry_count = 0;
+    let mut current_position = length_end + 2;
+    while read_entry_count < map_length {
+        let next_read_key = DataTypeRef::parse(input, current_position)?;
+        let next_read_value = DataTypeRef::parse(input, next_read_key.1)?;
+        entries.push((next_read_key.0, next_read_value.0));
+        current_position = next_read_value.1;
+        read_entry_count = read_entry_count + 1;
+    }
+    Ok((DataTypeRef::Map { entries }, current_position))
+}
+
+fn parse_ref_set(input: &[u8], position: usize) -> Result<(DataTypeRef<'_>, usize), anyhow::Error> {
+    read_and_assert_symbol(input, b'~', position).with_context(|| format!("Invalid Set at position {}", position))?;
+    let length_start = position + 1;
+    let length_end = find_crlf_or_incomplete(input, length_start)?;
+    let set_length: i64 = String::from_utf8_lossy(&input[length_start..length_end]).parse()?;
+    read_and_assert_symbol(input, b'\r', length_end).with_context(|| format!("Invalid Set at position {}", position))?;
+    read_and_assert_symbol(input, b'\n', length_end + 1).with_context(|| format!("Invalid Set at position {}", position))?;
+    let mut elements = Vec::new();
+    let mut read_element_count = 0;
+    let mut current_position = length_end + 2;
+    while read_element_count < set_length {
+        let (next_element, next_position) = DataTypeRef::parse(input, current_position)?;
+        elements.push(next_element);
+        read_element_count = read_element_count + 1;
+        current_position = next_position;
+    }
+    Ok((DataTypeRef::Set { elements }, current_position))
+}
+
+fn parse_ref_array_like(input: &[u8], position: usize, prefix: u8) -> Result<(Vec<DataTypeRef<'_>>, usize), anyhow::Error> {
+    read_and_assert_symbol(input, prefix, position).with_context(|| format!("Invalid Array-like at position {}", position))?;
+    let length_start = position + 1;
+    let length_end = find_crlf_or_incomplete(input, length_start)?;
+    let array_length: i64 = String::from_utf8_lossy(&input[length_start..length_end]).parse()?;
+    read_and_assert_symbol(input, b'\r', length_end).with_context(|| format!("Invalid Array-like at position {}", position))?;
+    read_and_assert_symbol(input, b'\n', length_end + 1).with_context(|| format!("Invalid Array-like at position {}", position))?;
+    let mut elements = Vec::new();
+    let mut read_element_count = 0;
+    let mut current_position = length_end + 2;
+    while read_element_count < array_length {
+        let next_read_element = DataTypeRef::parse(input, current_position)?;
+        elements.push(next_read_element.0);
+        current_position = next_read_element.1;
+        read_element_count = read_element_count + 1;
+    }
+    Ok((elements, current_position))
+}
+
+fn parse_ref_array(input: &[u8], position: usize) -> Result<(DataTypeRef<'_>, usize), anyhow::Error> {
+    let (elements, updated_position) = parse_ref_array_like(input, position, b'*')?;
+    Ok((DataTypeRef::Array { elements }, updated_position))
+}
+
+fn parse_ref_push(input: &[u8], position: usize) -> Result<(DataTypeRef<'_>, usize), anyhow::Error> {
+    let (elements, updated_position) = parse_ref_array_like(input, position, b'>')?;
+    Ok((DataTypeRef::Push { elements }, updated_position))
+}
+
+fn parse_ref_null(input: &[u8], position: usize) -> Result<(DataTypeRef<'_>, usize), anyhow::Error> {
+    read_and_assert_symbol(input, b'_', position).with_context(|| format!("Invalid Null at position {}", position))?;
+    read_and_assert_symbol(input, b'\r', position + 1).with_context(|| format!("Invalid Null at position {}", position))?;
+    read_and_assert_symbol(input, b'\n', position + 2).with_context(|| format!("Invalid Null at position {}", position))?;
+    Ok((DataTypeRef::Null, position + 3))
+}
+
+fn parse_ref_boolean(input: &[u8], position: usize) -> Result<(DataTypeRef<'_>, usize), anyhow::Error> {
+    read_and_assert_symbol(input, b'#', position).with_context(|| format!("Invalid Boolean at position {}", position))?;
+    let &value_input = input.get(position + 1).ok_or::<anyhow::Error>(Incomplete.into())?;
+    let value = value_input == b't';
+    read_and_assert_symbol(input, b'\r', position + 2).with_context(|| format!("Invalid Boolean at position {}", position))?;
+    read_and_assert_symbol(input, b'\n', position + 3).with_context(|| format!("Invalid Boolean at position {}", position))?;
+    Ok((DataTypeRef::Boolean { value }, position + 4))
+}
+
 #[cfg(test)]
 mod tests {
     use core::f64;
@@ -1084,13 +1628,32 @@ mod tests {
         assert_eq!(format!("{}", error), format!("RedisError: Could not read the next data type value '{}' at position 0, unsupported prefix 'a'", input))
     }
 
+    #[test]
+    fn should_read_byte_array_with_invalid_utf8() {
+        let invalid_utf8_key = vec![0xff, 0xfe, 0x00, 0x01];
+        let message = DataType::Array {
+            elements: vec![
+                DataType::BulkString { value: Some(b"SET".to_vec()) },
+                DataType::BulkString { value: Some(invalid_utf8_key.clone()) },
+                DataType::BulkString { value: Some(invalid_utf8_key.clone()) },
+            ]
+        };
+        assert_eq!(message.as_array().is_err(), true);
+        assert_eq!(message.as_byte_array().unwrap(), vec![
+            b"SET".to_vec(),
+            invalid_utf8_key.clone(),
+            invalid_utf8_key
+        ]);
+    }
+
     #[test]
     fn should_read_message_from_bytes() {
-        let parsed_single_message = read_messages_from_bytes(&"$5\r\nHello\r\n".as_bytes().to_vec()).unwrap();
+        let (parsed_single_message, consumed) = read_messages_from_bytes(&"$5\r\nHello\r\n".as_bytes().to_vec()).unwrap();
         assert_eq!(parsed_single_message, vec![DataType::BulkString {
             value: Some("Hello".as_bytes().to_vec())
         }]);
-        let parsed_messages = read_messages_from_bytes(&"$1\r\na\r\n$2\r\nbc\r\n$3\r\ndef\r\n".as_bytes().to_vec()).unwrap();
+        assert_eq!(consumed, 11);
+        let (parsed_messages, consumed) = read_messages_from_bytes(&"$1\r\na\r\n$2\r\nbc\r\n$3\r\ndef\r\n".as_bytes().to_vec()).unwrap();
         assert_eq!(parsed_messages, vec![DataType::BulkString {
             value: Some("a".as_bytes().to_vec())
         }, DataType::BulkString {
@@ -1098,5 +1661,201 @@ mod tests {
         }, DataType::BulkString {
             value: Some("def".as_bytes().to_vec())
         }]);
+        assert_eq!(consumed, 24);
+    }
+
+    #[test]
+    fn should_try_parse_report_incomplete_instead_of_erroring() {
+        match DataType::try_parse(&"$5\r\nHel".as_bytes().to_vec(), 0).unwrap() {
+            DecodeOutcome::Incomplete { needed_at_least } => assert!(needed_at_least >= 1),
+            other => panic!("expected Incomplete, got {:?}", other)
+        }
+        assert_eq!(DataType::try_parse(&"$5\r\nHello\r\n".as_bytes().to_vec(), 0).unwrap(), DecodeOutcome::Complete((DataType::BulkString {
+            value: Some("Hello".as_bytes().to_vec())
+        }, 11)));
+    }
+
+    #[test]
+    fn should_report_incomplete_until_every_byte_of_a_frame_has_arrived() {
+        let frames: Vec<Vec<u8>> = vec![
+            protocol_array_example(),
+            "$5\r\nHello\r\n".as_bytes().to_vec(),
+            ":42\r\n".as_bytes().to_vec(),
+            "+OK\r\n".as_bytes().to_vec(),
+        ];
+        for frame in frames {
+            for prefix_len in 0..frame.len() {
+                let prefix = frame[0..prefix_len].to_vec();
+                match DataType::try_parse(&prefix, 0) {
+                    Ok(DecodeOutcome::Incomplete { .. }) => (),
+                    other => panic!("expected Incomplete for prefix {:?} of {:?}, got {:?}", prefix, frame, other),
+                }
+            }
+            match DataType::try_parse(&frame, 0).unwrap() {
+                DecodeOutcome::Complete((_, consumed)) => assert_eq!(consumed, frame.len()),
+                other => panic!("expected Complete once every byte of {:?} has arrived, got {:?}", frame, other),
+            }
+        }
+    }
+
+    fn protocol_array_example() -> Vec<u8> {
+        array(vec![bulk_string("SET"), bulk_string("key"), bulk_string("value")]).serialize()
+    }
+
+    #[test]
+    fn should_preserve_invalid_utf8_bulk_string_content_without_validating_it() {
+        // A bulk string payload that is not valid UTF-8 must still round-trip
+        // byte-for-byte: the parser copies length-prefixed bytes raw rather
+        // than decoding them as a string anywhere on the parse path.
+        let invalid_utf8 = vec![0xff, 0xfe, b'x', 0x00, 0x80];
+        let mut frame = format!("${}\r\n", invalid_utf8.len()).into_bytes();
+        frame.extend_from_slice(&invalid_utf8);
+        frame.extend_from_slice(b"\r\n");
+
+        assert!(std::str::from_utf8(&invalid_utf8).is_err());
+        match DataType::try_parse(&frame, 0).unwrap() {
+            DecodeOutcome::Complete((DataType::BulkString { value: Some(bytes) }, consumed)) => {
+                assert_eq!(bytes, invalid_utf8);
+                assert_eq!(consumed, frame.len());
+            }
+            other => panic!("expected a complete BulkString, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn should_leave_a_trailing_partial_frame_unconsumed() {
+        let input = "$1\r\na\r\n$5\r\nHel".as_bytes().to_vec();
+        let (messages, consumed) = read_messages_from_bytes(&input).unwrap();
+        assert_eq!(messages, vec![DataType::BulkString { value: Some("a".as_bytes().to_vec()) }]);
+        assert_eq!(consumed, 7);
+        assert_eq!(&input[consumed..], "$5\r\nHel".as_bytes());
+    }
+
+    #[test]
+    fn should_report_incomplete_for_a_frame_split_across_reads() {
+        // Header itself hasn't arrived yet.
+        assert_eq!(is_incomplete(&DataType::parse(&"".as_bytes().to_vec(), 0).unwrap_err()), true);
+        assert_eq!(is_incomplete(&DataType::parse(&"$5\r\nHel".as_bytes().to_vec(), 0).unwrap_err()), true);
+        // Length header is present but its terminating CRLF hasn't arrived yet.
+        assert_eq!(is_incomplete(&DataType::parse(&"$5".as_bytes().to_vec(), 0).unwrap_err()), true);
+        // Content declared by the length header hasn't fully arrived yet.
+        assert_eq!(is_incomplete(&DataType::parse(&"$5\r\nHell".as_bytes().to_vec(), 0).unwrap_err()), true);
+        // A nested type inside an array is itself incomplete.
+        assert_eq!(is_incomplete(&DataType::parse(&"*2\r\n$5\r\nHello\r\n$5\r\nwor".as_bytes().to_vec(), 0).unwrap_err()), true);
+    }
+
+    #[test]
+    fn should_not_report_incomplete_for_genuinely_malformed_input() {
+        let error = DataType::parse(&"a+5\r\n".as_bytes().to_vec(), 0).unwrap_err();
+        assert_eq!(is_incomplete(&error), false);
+    }
+
+    #[test]
+    fn should_resume_parsing_once_the_rest_of_a_split_frame_arrives() {
+        let first_chunk = "$5\r\nHel".as_bytes().to_vec();
+        assert_eq!(is_incomplete(&DataType::parse(&first_chunk, 0).unwrap_err()), true);
+
+        let mut full_frame = first_chunk;
+        full_frame.extend("lo\r\n".as_bytes());
+        assert_eq!(DataType::parse(&full_frame, 0).unwrap(), (DataType::BulkString {
+            value: Some("Hello".as_bytes().to_vec())
+        }, 11));
+    }
+
+    #[test]
+    fn should_parse_bulk_string_ref_without_copying() {
+        let input = "$5\r\nHello\r\n".as_bytes().to_vec();
+        let (parsed, consumed) = DataTypeRef::parse(&input, 0).unwrap();
+        assert_eq!(parsed, DataTypeRef::BulkString { value: Some("Hello".as_bytes()) });
+        assert_eq!(consumed, 11);
+        assert_eq!(parsed.to_owned(), DataType::BulkString { value: Some("Hello".as_bytes().to_vec()) });
+    }
+
+    #[test]
+    fn should_parse_nested_array_ref() {
+        let input = "*2\r\n$5\r\nhello\r\n$5\r\nworld\r\n".as_bytes().to_vec();
+        let (parsed, consumed) = DataTypeRef::parse(&input, 0).unwrap();
+        assert_eq!(parsed, DataTypeRef::Array {
+            elements: vec![
+                DataTypeRef::BulkString { value: Some("hello".as_bytes()) },
+                DataTypeRef::BulkString { value: Some("world".as_bytes()) }
+            ]
+        });
+        assert_eq!(consumed, 26);
+        assert_eq!(parsed.to_owned(), DataType::Array {
+            elements: vec![
+                DataType::BulkString { value: Some("hello".as_bytes().to_vec()) },
+                DataType::BulkString { value: Some("world".as_bytes().to_vec()) }
+            ]
+        });
+    }
+
+    #[test]
+    fn should_report_incomplete_for_a_ref_frame_split_across_reads() {
+        assert_eq!(is_incomplete(&DataTypeRef::parse(&"$5\r\nHel".as_bytes().to_vec(), 0).unwrap_err()), true);
+    }
+
+    #[test]
+    fn should_serialize_resp3_types_unchanged_as_resp3() {
+        let value = DataType::Boolean { value: true };
+        assert_eq!(value.serialize_as(RespVersion::Resp3), value.serialize());
+    }
+
+    #[test]
+    fn should_downgrade_map_to_flattened_array() {
+        let value = DataType::Map {
+            entries: vec![
+                (DataType::Integer { value: 1 }, DataType::BulkString { value: Some("hello".as_bytes().to_vec()) })
+            ]
+        };
+        assert_eq!(String::from_utf8_lossy(&value.serialize_as(RespVersion::Resp2)), "*2\r\n:1\r\n$5\r\nhello\r\n".to_string());
+    }
+
+    #[test]
+    fn should_downgrade_set_to_array() {
+        let value = DataType::Set {
+            elements: vec![DataType::Integer { value: 1 }, DataType::Integer { value: 2 }]
+        };
+        assert_eq!(String::from_utf8_lossy(&value.serialize_as(RespVersion::Resp2)), "*2\r\n:1\r\n:2\r\n".to_string());
+    }
+
+    #[test]
+    fn should_downgrade_push_to_array() {
+        let value = DataType::Push {
+            elements: vec![DataType::Integer { value: 1 }]
+        };
+        assert_eq!(String::from_utf8_lossy(&value.serialize_as(RespVersion::Resp2)), "*1\r\n:1\r\n".to_string());
+    }
+
+    #[test]
+    fn should_downgrade_boolean_to_integer() {
+        assert_eq!(String::from_utf8_lossy(&DataType::Boolean { value: true }.serialize_as(RespVersion::Resp2)), ":1\r\n".to_string());
+        assert_eq!(String::from_utf8_lossy(&DataType::Boolean { value: false }.serialize_as(RespVersion::Resp2)), ":0\r\n".to_string());
+    }
+
+    #[test]
+    fn should_downgrade_double_and_big_number_to_bulk_strings() {
+        assert_eq!(String::from_utf8_lossy(&DataType::Double { value: 1.23 }.serialize_as(RespVersion::Resp2)), "$4\r\n1.23\r\n".to_string());
+        assert_eq!(String::from_utf8_lossy(&DataType::BigNumber { sign: b'-', value: "349".as_bytes().to_vec() }.serialize_as(RespVersion::Resp2)), "$4\r\n-349\r\n".to_string());
+    }
+
+    #[test]
+    fn should_downgrade_null_to_null_bulk_string() {
+        assert_eq!(String::from_utf8_lossy(&DataType::Null.serialize_as(RespVersion::Resp2)), "$-1\r\n".to_string());
+    }
+
+    #[test]
+    fn should_downgrade_verbatim_string_to_plain_bulk_string() {
+        let value = DataType::VerbatimString {
+            encoding: "txt".as_bytes().to_vec(),
+            value: "Some string".as_bytes().to_vec()
+        };
+        assert_eq!(String::from_utf8_lossy(&value.serialize_as(RespVersion::Resp2)), "$11\r\nSome string\r\n".to_string());
+    }
+
+    #[test]
+    fn should_downgrade_bulk_error_to_simple_error() {
+        let value = DataType::BulkError { value: "Some error".as_bytes().to_vec() };
+        assert_eq!(String::from_utf8_lossy(&value.serialize_as(RespVersion::Resp2)), "-Some error\r\n".to_string());
     }
 }
@@ -6,11 +6,13 @@
 use anyhow::{anyhow, ensure};
 use std::io::Write;
 use std::net::TcpStream;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::Mutex;
 
 use crate::protocol;
 use crate::io;
+use crate::secure_transport;
 use crate::storage::Storage;
 use crate::server_state::ServerState;
 
@@ -31,18 +33,24 @@ use crate::server_state::ServerState;
 ///
 /// # Returns
 /// Error if handshake fails or connection is lost
-pub fn join_replica(
+pub async fn join_replica(
     master_address: &str,
     server_state: &Arc<ServerState>,
     storage: &Arc<Mutex<Storage>>,
 ) -> Result<(), anyhow::Error> {
-    let mut stream = TcpStream::connect(master_address)?;
+    let stream = TcpStream::connect(master_address)?;
     stream.set_read_timeout(Some(Duration::new(5, 0)))?;
 
+    let (secure, network_key) = {
+        let config = server_state.config.lock().map_err(|e| anyhow!("Failed to lock config: {}", e))?;
+        (config.secure == "yes", config.network_key.clone())
+    };
+    let (mut reader, writer) = secure_transport::negotiate_client(stream, secure, network_key.as_bytes(), &server_state.node_identity)?;
+
     // Step 1: PING handshake
     let ping = protocol::array(vec![protocol::bulk_string("PING")]);
-    stream.write_all(&ping.serialize())?;
-    if let Some(pong) = io::read_single_message(&mut stream)? {
+    writer.lock().map_err(|e| anyhow!("Failed to lock replication writer: {}", e))?.write_all(&ping.serialize())?;
+    if let Some(pong) = io::read_single_message(&mut reader)? {
         ensure!(
             pong.as_string()? == "PONG",
             "Should receive PONG from the master node"
@@ -57,8 +65,8 @@ pub fn join_replica(
         protocol::bulk_string("listening-port"),
         protocol::bulk_string(&server_state.port.to_string()),
     ]);
-    stream.write_all(&port_replconf.serialize())?;
-    if let Some(ok) = io::read_single_message(&mut stream)? {
+    writer.lock().map_err(|e| anyhow!("Failed to lock replication writer: {}", e))?.write_all(&port_replconf.serialize())?;
+    if let Some(ok) = io::read_single_message(&mut reader)? {
         ensure!(
             ok.as_string()? == "OK",
             "Should receive OK from the master node for listening-port"
@@ -73,8 +81,8 @@ pub fn join_replica(
         protocol::bulk_string("capa"),
         protocol::bulk_string("psync2"),
     ]);
-    stream.write_all(&capa_replconf.serialize())?;
-    if let Some(ok) = io::read_single_message(&mut stream)? {
+    writer.lock().map_err(|e| anyhow!("Failed to lock replication writer: {}", e))?.write_all(&capa_replconf.serialize())?;
+    if let Some(ok) = io::read_single_message(&mut reader)? {
         ensure!(
             ok.as_string()? == "OK",
             "Should receive OK from the master node for capa"
@@ -89,12 +97,12 @@ pub fn join_replica(
         protocol::bulk_string("?"),
         protocol::bulk_string("-1"),
     ]);
-    stream.write_all(&psync.serialize())?;
+    writer.lock().map_err(|e| anyhow!("Failed to lock replication writer: {}", e))?.write_all(&psync.serialize())?;
 
     println!("Replica listening for commands from master...");
-    
+
     // Step 5-6: Receive RDB and enter replication loop
-    crate::connection::handle_connection(&mut stream, storage, server_state, false)?;
+    crate::connection::handle_connection(&mut reader, &writer, storage, server_state, false).await?;
     Ok(())
 }
 
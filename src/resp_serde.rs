@@ -0,0 +1,633 @@
+/// Serde `Serialize`/`Deserialize` support for `DataType`.
+///
+/// This lets Rust values that derive `Serialize`/`Deserialize` encode
+/// straight to RESP (via [`to_data_type`], then `DataType::serialize`) and
+/// be decoded back out of an already-parsed `DataType` (via
+/// [`from_data_type`]), instead of every command hand-assembling
+/// `protocol::array`/`protocol::bulk_string` calls and hand-picking fields
+/// back out of `DataType::Array`/`DataType::Map`.
+///
+/// Values map onto RESP3 types the same way `serde_json` maps Rust values
+/// onto JSON: strings and byte slices become `BulkString`, sequences become
+/// `Array`, maps and structs become `Map`, `Option::None`/unit become
+/// `Null`, and enum variants follow `serde_json`'s convention (a plain
+/// string for unit variants, a single-entry map keyed by the variant name
+/// otherwise).
+use std::str;
+
+use serde::{de, ser, Deserialize, Serialize};
+
+use crate::error::RedisError;
+use crate::protocol::DataType;
+
+pub fn to_data_type<T>(value: &T) -> Result<DataType, anyhow::Error>
+where
+    T: ?Sized + Serialize,
+{
+    Ok(value.serialize(Serializer)?)
+}
+
+pub fn from_data_type<'de, T>(input: &'de DataType) -> Result<T, anyhow::Error>
+where
+    T: Deserialize<'de>,
+{
+    Ok(T::deserialize(Deserializer { input })?)
+}
+
+fn bulk_string_key(name: &str) -> DataType {
+    DataType::BulkString { value: Some(name.as_bytes().to_vec()) }
+}
+
+// ===================== Serializer =====================
+
+pub struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = DataType;
+    type Error = RedisError;
+
+    type SerializeSeq = SerializeVec;
+    type SerializeTuple = SerializeVec;
+    type SerializeTupleStruct = SerializeVec;
+    type SerializeTupleVariant = SerializeTupleVariant;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeStructVariant;
+
+    fn serialize_bool(self, value: bool) -> Result<DataType, RedisError> {
+        Ok(DataType::Boolean { value })
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<DataType, RedisError> {
+        self.serialize_i64(value as i64)
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<DataType, RedisError> {
+        self.serialize_i64(value as i64)
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<DataType, RedisError> {
+        self.serialize_i64(value as i64)
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<DataType, RedisError> {
+        Ok(DataType::Integer { value })
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<DataType, RedisError> {
+        self.serialize_i64(value as i64)
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<DataType, RedisError> {
+        self.serialize_i64(value as i64)
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<DataType, RedisError> {
+        self.serialize_i64(value as i64)
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<DataType, RedisError> {
+        Ok(DataType::Integer { value: value as i64 })
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<DataType, RedisError> {
+        self.serialize_f64(value as f64)
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<DataType, RedisError> {
+        Ok(DataType::Double { value })
+    }
+
+    fn serialize_char(self, value: char) -> Result<DataType, RedisError> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_str(self, value: &str) -> Result<DataType, RedisError> {
+        Ok(DataType::BulkString { value: Some(value.as_bytes().to_vec()) })
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<DataType, RedisError> {
+        Ok(DataType::BulkString { value: Some(value.to_vec()) })
+    }
+
+    fn serialize_none(self) -> Result<DataType, RedisError> {
+        Ok(DataType::Null)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<DataType, RedisError>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<DataType, RedisError> {
+        Ok(DataType::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<DataType, RedisError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str) -> Result<DataType, RedisError> {
+        Ok(DataType::SimpleString { value: variant.as_bytes().to_vec() })
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<DataType, RedisError>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(self, _name: &'static str, _variant_index: u32, variant: &'static str, value: &T) -> Result<DataType, RedisError>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(DataType::Map { entries: vec![(bulk_string_key(variant), value.serialize(Serializer)?)] })
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeVec, RedisError> {
+        Ok(SerializeVec { elements: Vec::with_capacity(len.unwrap_or(0)) })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeVec, RedisError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeVec, RedisError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str, len: usize) -> Result<SerializeTupleVariant, RedisError> {
+        Ok(SerializeTupleVariant { variant, elements: Vec::with_capacity(len) })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap, RedisError> {
+        Ok(SerializeMap { entries: Vec::new(), next_key: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap, RedisError> {
+        Ok(SerializeMap { entries: Vec::with_capacity(len), next_key: None })
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str, len: usize) -> Result<SerializeStructVariant, RedisError> {
+        Ok(SerializeStructVariant { variant, entries: Vec::with_capacity(len) })
+    }
+}
+
+pub struct SerializeVec {
+    elements: Vec<DataType>,
+}
+
+impl ser::SerializeSeq for SerializeVec {
+    type Ok = DataType;
+    type Error = RedisError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), RedisError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.elements.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<DataType, RedisError> {
+        Ok(DataType::Array { elements: self.elements })
+    }
+}
+
+impl ser::SerializeTuple for SerializeVec {
+    type Ok = DataType;
+    type Error = RedisError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), RedisError>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<DataType, RedisError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeVec {
+    type Ok = DataType;
+    type Error = RedisError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), RedisError>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<DataType, RedisError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+pub struct SerializeTupleVariant {
+    variant: &'static str,
+    elements: Vec<DataType>,
+}
+
+impl ser::SerializeTupleVariant for SerializeTupleVariant {
+    type Ok = DataType;
+    type Error = RedisError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), RedisError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.elements.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<DataType, RedisError> {
+        Ok(DataType::Map { entries: vec![(bulk_string_key(self.variant), DataType::Array { elements: self.elements })] })
+    }
+}
+
+pub struct SerializeMap {
+    entries: Vec<(DataType, DataType)>,
+    next_key: Option<DataType>,
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = DataType;
+    type Error = RedisError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), RedisError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.next_key = Some(key.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), RedisError>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = self.next_key.take().ok_or_else(|| RedisError {
+            message: "serialize_value was called before serialize_key".to_string()
+        })?;
+        self.entries.push((key, value.serialize(Serializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<DataType, RedisError> {
+        Ok(DataType::Map { entries: self.entries })
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = DataType;
+    type Error = RedisError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), RedisError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.entries.push((bulk_string_key(key), value.serialize(Serializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<DataType, RedisError> {
+        Ok(DataType::Map { entries: self.entries })
+    }
+}
+
+pub struct SerializeStructVariant {
+    variant: &'static str,
+    entries: Vec<(DataType, DataType)>,
+}
+
+impl ser::SerializeStructVariant for SerializeStructVariant {
+    type Ok = DataType;
+    type Error = RedisError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), RedisError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.entries.push((bulk_string_key(key), value.serialize(Serializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<DataType, RedisError> {
+        Ok(DataType::Map { entries: vec![(bulk_string_key(self.variant), DataType::Map { entries: self.entries })] })
+    }
+}
+
+// ===================== Deserializer =====================
+
+pub struct Deserializer<'de> {
+    input: &'de DataType,
+}
+
+impl<'de> Deserializer<'de> {
+    pub fn new(input: &'de DataType) -> Self {
+        Deserializer { input }
+    }
+}
+
+fn as_str(data_type: &DataType) -> Result<&str, RedisError> {
+    let bytes: &[u8] = match data_type {
+        DataType::SimpleString { value } => value,
+        DataType::BulkString { value: Some(value) } => value,
+        DataType::SimpleError { value } => value,
+        DataType::BulkError { value } => value,
+        other => return Err(RedisError { message: format!("expected a string-like RESP type, got {:?}", other) })
+    };
+    str::from_utf8(bytes).map_err(|error| RedisError { message: error.to_string() })
+}
+
+fn visit_bytes_or_str<'de, V>(bytes: &'de [u8], visitor: V) -> Result<V::Value, RedisError>
+where
+    V: de::Visitor<'de>,
+{
+    match str::from_utf8(bytes) {
+        Ok(text) => visitor.visit_borrowed_str(text),
+        Err(_) => visitor.visit_borrowed_bytes(bytes)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = RedisError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, RedisError>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.input {
+            DataType::Null => visitor.visit_unit(),
+            DataType::Boolean { value } => visitor.visit_bool(*value),
+            DataType::Integer { value } => visitor.visit_i64(*value),
+            DataType::Double { value } => visitor.visit_f64(*value),
+            DataType::BulkString { value: None } => visitor.visit_none(),
+            DataType::BulkString { value: Some(value) } => visit_bytes_or_str(value, visitor),
+            DataType::Rdb { value } => visit_bytes_or_str(value, visitor),
+            DataType::SimpleString { value } => visit_bytes_or_str(value, visitor),
+            DataType::SimpleError { value } => visit_bytes_or_str(value, visitor),
+            DataType::BulkError { value } => visit_bytes_or_str(value, visitor),
+            DataType::VerbatimString { value, .. } => visit_bytes_or_str(value, visitor),
+            DataType::BigNumber { sign, value } => {
+                let mut text = Vec::with_capacity(value.len() + 1);
+                if *sign == b'-' {
+                    text.push(b'-');
+                }
+                text.extend(value);
+                visit_bytes_or_str(&text, visitor)
+            },
+            DataType::Array { elements } | DataType::Set { elements } | DataType::Push { elements } => {
+                visitor.visit_seq(SeqAccess { iter: elements.iter() })
+            },
+            DataType::Map { entries } => visitor.visit_map(MapAccess { iter: entries.iter(), value: None })
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, RedisError>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.input {
+            DataType::Null | DataType::BulkString { value: None } => visitor.visit_none(),
+            _ => visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_enum<V>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, RedisError>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.input {
+            DataType::Map { entries } if entries.len() == 1 => {
+                let (variant, value) = &entries[0];
+                visitor.visit_enum(EnumAccess { variant, value: Some(value) })
+            },
+            DataType::Map { entries } => Err(RedisError {
+                message: format!("expected a single-entry Map for an enum variant, got {} entries", entries.len())
+            }),
+            other => visitor.visit_enum(EnumAccess { variant: other, value: None })
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct SeqAccess<'de> {
+    iter: std::slice::Iter<'de, DataType>,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'de> {
+    type Error = RedisError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, RedisError>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some(value) => seed.deserialize(Deserializer { input: value }).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        match self.iter.size_hint() {
+            (lower, Some(upper)) if lower == upper => Some(upper),
+            _ => None
+        }
+    }
+}
+
+struct MapAccess<'de> {
+    iter: std::slice::Iter<'de, (DataType, DataType)>,
+    value: Option<&'de DataType>,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'de> {
+    type Error = RedisError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, RedisError>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(Deserializer { input: key }).map(Some)
+            },
+            None => Ok(None)
+        }
+    }
+
+    fn next_value_seed<T>(&mut self, seed: T) -> Result<T::Value, RedisError>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        let value = self.value.take().ok_or_else(|| RedisError {
+            message: "next_value_seed was called before next_key_seed".to_string()
+        })?;
+        seed.deserialize(Deserializer { input: value })
+    }
+}
+
+struct EnumAccess<'de> {
+    variant: &'de DataType,
+    value: Option<&'de DataType>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
+    type Error = RedisError;
+    type Variant = VariantAccess<'de>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, VariantAccess<'de>), RedisError>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant_name = as_str(self.variant)?;
+        let variant = seed.deserialize(StrDeserializer { value: variant_name })?;
+        Ok((variant, VariantAccess { value: self.value }))
+    }
+}
+
+struct VariantAccess<'de> {
+    value: Option<&'de DataType>,
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess<'de> {
+    type Error = RedisError;
+
+    fn unit_variant(self) -> Result<(), RedisError> {
+        match self.value {
+            None => Ok(()),
+            Some(other) => Err(RedisError { message: format!("expected a unit variant, got {:?}", other) })
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, RedisError>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.value {
+            Some(value) => seed.deserialize(Deserializer { input: value }),
+            None => Err(RedisError { message: "expected a newtype variant value".to_string() })
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, RedisError>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.value {
+            Some(DataType::Array { elements }) => visitor.visit_seq(SeqAccess { iter: elements.iter() }),
+            Some(other) => Err(RedisError { message: format!("expected an Array for a tuple variant, got {:?}", other) }),
+            None => Err(RedisError { message: "expected a tuple variant value".to_string() })
+        }
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, RedisError>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.value {
+            Some(DataType::Map { entries }) => visitor.visit_map(MapAccess { iter: entries.iter(), value: None }),
+            Some(other) => Err(RedisError { message: format!("expected a Map for a struct variant, got {:?}", other) }),
+            None => Err(RedisError { message: "expected a struct variant value".to_string() })
+        }
+    }
+}
+
+struct StrDeserializer<'de> {
+    value: &'de str,
+}
+
+impl<'de> de::Deserializer<'de> for StrDeserializer<'de> {
+    type Error = RedisError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, RedisError>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_borrowed_str(self.value)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Entry {
+        key: String,
+        value: i64,
+        tags: Vec<String>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Reply {
+        Ok,
+        Error(String),
+    }
+
+    #[test]
+    fn should_round_trip_a_struct_through_a_map() {
+        let entry = Entry {
+            key: "counter".to_string(),
+            value: 42,
+            tags: vec!["a".to_string(), "b".to_string()],
+        };
+
+        let data_type = to_data_type(&entry).unwrap();
+        assert_eq!(data_type, DataType::Map {
+            entries: vec![
+                (bulk_string_key("key"), DataType::BulkString { value: Some(b"counter".to_vec()) }),
+                (bulk_string_key("value"), DataType::Integer { value: 42 }),
+                (bulk_string_key("tags"), DataType::Array { elements: vec![
+                    DataType::BulkString { value: Some(b"a".to_vec()) },
+                    DataType::BulkString { value: Some(b"b".to_vec()) },
+                ] }),
+            ]
+        });
+
+        let round_tripped: Entry = from_data_type(&data_type).unwrap();
+        assert_eq!(round_tripped, entry);
+    }
+
+    #[test]
+    fn should_round_trip_unit_and_newtype_enum_variants() {
+        let ok_data_type = to_data_type(&Reply::Ok).unwrap();
+        assert_eq!(ok_data_type, DataType::SimpleString { value: b"Ok".to_vec() });
+        assert_eq!(from_data_type::<Reply>(&ok_data_type).unwrap(), Reply::Ok);
+
+        let error_data_type = to_data_type(&Reply::Error("boom".to_string())).unwrap();
+        assert_eq!(error_data_type, DataType::Map {
+            entries: vec![(bulk_string_key("Error"), DataType::BulkString { value: Some(b"boom".to_vec()) })]
+        });
+        assert_eq!(from_data_type::<Reply>(&error_data_type).unwrap(), Reply::Error("boom".to_string()));
+    }
+
+    #[test]
+    fn should_round_trip_an_option() {
+        let present: Option<i64> = Some(7);
+        let absent: Option<i64> = None;
+
+        assert_eq!(from_data_type::<Option<i64>>(&to_data_type(&present).unwrap()).unwrap(), present);
+        assert_eq!(from_data_type::<Option<i64>>(&to_data_type(&absent).unwrap()).unwrap(), absent);
+    }
+}
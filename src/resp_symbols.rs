@@ -0,0 +1,238 @@
+/// Optional symbol-interning layer on top of the standard RESP wire format.
+///
+/// `Map`/`Push` payloads built from Rust structs (COMMAND DOCS, CLIENT INFO,
+/// XINFO, ...) repeat the same field-name bulk strings across every entry.
+/// `serialize_with_symbols` walks a `DataType` tree and, the second and
+/// later time it sees an identical bulk-string payload, emits a compact
+/// back-reference (`^<index>\r\n`) instead of the literal bytes again;
+/// `parse_with_symbols` walks the result and resolves those back-references
+/// against the bulk strings it has already decoded. Both build their own
+/// `SymbolTable` and start from an empty one, so this is purely an
+/// encode/decode pair layered over `DataType` - nothing here changes what
+/// `DataType::serialize`/`DataType::parse` do, and a connection only pays
+/// for interning if it opts in by calling these instead.
+use std::collections::HashMap;
+
+use crate::error::RedisError;
+use crate::protocol::{DataType, Incomplete};
+
+const SYMBOL_REF_PREFIX: u8 = b'^';
+
+/// Tracks bulk-string payloads already seen during one `serialize_with_symbols`
+/// or `parse_with_symbols` pass, in the order each was first encountered.
+/// Both sides assign indices the same way (first occurrence gets the next
+/// index in `Vec` order), so a back-reference index means the same thing on
+/// encode and decode without the two sides needing to agree on anything else.
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    values: Vec<Vec<u8>>,
+    index_by_value: HashMap<Vec<u8>, usize>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        SymbolTable {
+            values: Vec::new(),
+            index_by_value: HashMap::new(),
+        }
+    }
+
+    fn index_of(&self, value: &[u8]) -> Option<usize> {
+        self.index_by_value.get(value).copied()
+    }
+
+    fn intern(&mut self, value: Vec<u8>) -> usize {
+        let index = self.values.len();
+        self.index_by_value.insert(value.clone(), index);
+        self.values.push(value);
+        index
+    }
+
+    fn resolve(&self, index: usize) -> Option<&Vec<u8>> {
+        self.values.get(index)
+    }
+}
+
+pub fn serialize_with_symbols(value: &DataType) -> Vec<u8> {
+    let mut table = SymbolTable::new();
+    let mut result = Vec::new();
+    serialize_into(value, &mut table, &mut result);
+    result
+}
+
+pub fn parse_with_symbols(input: &Vec<u8>, position: usize) -> Result<(DataType, usize), anyhow::Error> {
+    let mut table = SymbolTable::new();
+    parse_into(input, position, &mut table)
+}
+
+fn serialize_into(value: &DataType, table: &mut SymbolTable, out: &mut Vec<u8>) {
+    match value {
+        DataType::BulkString { value: Some(bytes) } => {
+            match table.index_of(bytes) {
+                Some(index) => out.extend(serialize_symbol_ref(index)),
+                None => {
+                    table.intern(bytes.clone());
+                    out.extend(value.serialize());
+                }
+            }
+        },
+        DataType::Map { entries } => {
+            out.push(b'%');
+            out.extend(entries.len().to_string().as_bytes());
+            out.extend(b"\r\n");
+            for (key, entry_value) in entries.iter() {
+                serialize_into(key, table, out);
+                serialize_into(entry_value, table, out);
+            }
+        },
+        DataType::Set { elements } => serialize_array_like_into(elements, b'~', table, out),
+        DataType::Array { elements } => serialize_array_like_into(elements, b'*', table, out),
+        DataType::Push { elements } => serialize_array_like_into(elements, b'>', table, out),
+        _ => out.extend(value.serialize())
+    }
+}
+
+fn serialize_array_like_into(elements: &Vec<DataType>, prefix: u8, table: &mut SymbolTable, out: &mut Vec<u8>) {
+    out.push(prefix);
+    out.extend(elements.len().to_string().as_bytes());
+    out.extend(b"\r\n");
+    for element in elements.iter() {
+        serialize_into(element, table, out);
+    }
+}
+
+fn serialize_symbol_ref(index: usize) -> Vec<u8> {
+    let mut result = vec![SYMBOL_REF_PREFIX];
+    result.extend(index.to_string().as_bytes());
+    result.extend(b"\r\n");
+    result
+}
+
+fn parse_into(input: &Vec<u8>, position: usize, table: &mut SymbolTable) -> Result<(DataType, usize), anyhow::Error> {
+    match input.get(position) {
+        Some(&SYMBOL_REF_PREFIX) => parse_symbol_ref(input, position, table),
+        Some(b'$') => {
+            let (parsed, new_position) = DataType::parse(input, position)?;
+            if let DataType::BulkString { value: Some(bytes) } = &parsed {
+                table.intern(bytes.clone());
+            }
+            Ok((parsed, new_position))
+        },
+        Some(b'%') => parse_map_with_symbols(input, position, table),
+        Some(b'~') => parse_array_like_with_symbols(input, position, table)
+            .map(|(elements, new_position)| (DataType::Set { elements }, new_position)),
+        Some(b'*') => parse_array_like_with_symbols(input, position, table)
+            .map(|(elements, new_position)| (DataType::Array { elements }, new_position)),
+        Some(b'>') => parse_array_like_with_symbols(input, position, table)
+            .map(|(elements, new_position)| (DataType::Push { elements }, new_position)),
+        _ => DataType::parse(input, position)
+    }
+}
+
+fn parse_symbol_ref(input: &Vec<u8>, position: usize, table: &SymbolTable) -> Result<(DataType, usize), anyhow::Error> {
+    let error_message = format!("Invalid symbol reference at position {}", position);
+    let digits_start = position + 1;
+    let digits_end = find_crlf_or_incomplete(input, digits_start)?;
+    let index: usize = std::str::from_utf8(&input[digits_start..digits_end])?.parse()?;
+    let bytes = table.resolve(index).ok_or_else(|| RedisError {
+        message: format!("{}: no symbol interned yet at index {}", error_message, index)
+    })?.clone();
+    Ok((DataType::BulkString { value: Some(bytes) }, digits_end + 2))
+}
+
+fn parse_map_with_symbols(input: &Vec<u8>, position: usize, table: &mut SymbolTable) -> Result<(DataType, usize), anyhow::Error> {
+    let length_start = position + 1;
+    let length_end = find_crlf_or_incomplete(input, length_start)?;
+    let entry_count: usize = std::str::from_utf8(&input[length_start..length_end])?.parse()?;
+    let mut entries: Vec<(DataType, DataType)> = Vec::new();
+    let mut current_position = length_end + 2;
+    for _ in 0..entry_count {
+        let (key, position_after_key) = parse_into(input, current_position, table)?;
+        let (entry_value, position_after_value) = parse_into(input, position_after_key, table)?;
+        entries.push((key, entry_value));
+        current_position = position_after_value;
+    }
+    Ok((DataType::Map { entries }, current_position))
+}
+
+fn parse_array_like_with_symbols(input: &Vec<u8>, position: usize, table: &mut SymbolTable) -> Result<(Vec<DataType>, usize), anyhow::Error> {
+    let length_start = position + 1;
+    let length_end = find_crlf_or_incomplete(input, length_start)?;
+    let element_count: usize = std::str::from_utf8(&input[length_start..length_end])?.parse()?;
+    let mut elements: Vec<DataType> = Vec::new();
+    let mut current_position = length_end + 2;
+    for _ in 0..element_count {
+        let (element, position_after_element) = parse_into(input, current_position, table)?;
+        elements.push(element);
+        current_position = position_after_element;
+    }
+    Ok((elements, current_position))
+}
+
+fn find_crlf_or_incomplete(input: &[u8], position: usize) -> Result<usize, anyhow::Error> {
+    input[position.min(input.len())..]
+        .windows(2)
+        .position(|window| window == b"\r\n")
+        .map(|offset| position + offset)
+        .ok_or_else(|| Incomplete.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{array, bulk_string};
+
+    fn field(name: &str, value: &str) -> (DataType, DataType) {
+        (bulk_string(name), bulk_string(value))
+    }
+
+    #[test]
+    fn should_round_trip_map_with_repeated_keys() {
+        let value = DataType::Array {
+            elements: vec![
+                DataType::Map { entries: vec![field("name", "alice"), field("age", "30")] },
+                DataType::Map { entries: vec![field("name", "bob"), field("age", "25")] }
+            ]
+        };
+        let serialized = serialize_with_symbols(&value);
+        let (parsed, consumed) = parse_with_symbols(&serialized, 0).unwrap();
+        assert_eq!(parsed, value);
+        assert_eq!(consumed, serialized.len());
+    }
+
+    #[test]
+    fn should_back_reference_repeated_bulk_strings_in_an_array() {
+        let value = array(vec![bulk_string("hello"), bulk_string("hello"), bulk_string("hello")]);
+        let serialized = serialize_with_symbols(&value);
+        // Only the first "hello" is sent as a literal bulk string; every
+        // repeat collapses to the 4-byte back-reference "^0\r\n".
+        assert_eq!(serialized, "*3\r\n$5\r\nhello\r\n^0\r\n^0\r\n".as_bytes().to_vec());
+        let (parsed, consumed) = parse_with_symbols(&serialized, 0).unwrap();
+        assert_eq!(parsed, value);
+        assert_eq!(consumed, serialized.len());
+    }
+
+    #[test]
+    fn should_round_trip_nested_push_with_heavy_key_repetition() {
+        let value = DataType::Push {
+            elements: vec![
+                DataType::Map { entries: vec![field("type", "message"), field("channel", "news")] },
+                DataType::Push {
+                    elements: vec![
+                        DataType::Map { entries: vec![field("type", "message"), field("channel", "news")] }
+                    ]
+                }
+            ]
+        };
+        let serialized = serialize_with_symbols(&value);
+        let (parsed, consumed) = parse_with_symbols(&serialized, 0).unwrap();
+        assert_eq!(parsed, value);
+        assert_eq!(consumed, serialized.len());
+    }
+
+    #[test]
+    fn should_serialize_without_symbols_the_same_as_plain_serialize_when_nothing_repeats() {
+        let value = array(vec![bulk_string("a"), bulk_string("b"), bulk_string("c")]);
+        assert_eq!(serialize_with_symbols(&value), value.serialize());
+    }
+}
@@ -1,21 +1,176 @@
-use std::net::TcpStream;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::Write;
 
 use std::sync::{Arc, Mutex};
 use rand::Rng;
+use crate::aof::AofWriter;
+use crate::cluster::PeerTable;
+use crate::config::{self, ServerConfig};
 use crate::error::RedisError;
+use crate::protocol;
+use crate::secure_transport::{NodeIdentity, WriteHandle};
+use crate::storage;
+
+/// A fixed-size circular buffer of replicated command bytes.
+///
+/// The master appends the serialized bytes of every command it propagates to
+/// replicas here, and tracks `start_offset`, the replication offset of the
+/// oldest byte still retained. A reconnecting replica can request a partial
+/// resync by offset as long as that offset is still `>= start_offset`.
+pub struct ReplicationBacklog {
+    buffer: VecDeque<u8>,
+    capacity: usize,
+    start_offset: usize,
+    current_offset: usize
+}
+
+impl ReplicationBacklog {
+    pub fn new(capacity: usize) -> ReplicationBacklog {
+        ReplicationBacklog {
+            buffer: VecDeque::with_capacity(capacity),
+            capacity,
+            start_offset: 0,
+            current_offset: 0
+        }
+    }
+
+    /// Appends newly propagated bytes, advancing `current_offset` by their
+    /// exact length and dropping the oldest bytes once the backlog exceeds
+    /// its capacity (advancing `start_offset` accordingly).
+    pub fn append(&mut self, bytes: &[u8]) {
+        self.buffer.extend(bytes.iter().copied());
+        self.current_offset += bytes.len();
+        while self.buffer.len() > self.capacity {
+            self.buffer.pop_front();
+            self.start_offset += 1;
+        }
+    }
+
+    /// Returns the first byte offset still retained in the backlog.
+    pub fn start_offset(&self) -> usize {
+        self.start_offset
+    }
+
+    /// Returns the replication offset of the most recently appended byte.
+    pub fn current_offset(&self) -> usize {
+        self.current_offset
+    }
+
+    /// Returns the backlog bytes from `offset` onward, or `None` if `offset`
+    /// has already fallen out of the retained window or is in the future.
+    pub fn slice_from(&self, offset: usize) -> Option<Vec<u8>> {
+        if offset < self.start_offset || offset > self.current_offset {
+            None
+        } else {
+            Some(self.buffer.iter().skip(offset - self.start_offset).copied().collect())
+        }
+    }
+}
+
+/// A replica connection tracked by the master, together with the last
+/// replication offset that replica has acknowledged via `REPLCONF ACK`.
+///
+/// `stream` is the same `WriteHandle` the replica's own connection loop
+/// sends its replies through (see `secure_transport::TransportWriter`'s doc
+/// comment), shared here so propagation keeps going through
+/// `secure_transport`'s encryption when it's in use instead of writing
+/// plaintext bytes onto an encrypted session.
+pub struct ReplicaConnection {
+    pub stream: WriteHandle,
+    pub peer_address: String,
+    pub acked_offset: usize,
+    /// Whether this replica advertised the `eof` capability via `REPLCONF
+    /// capa` before `PSYNC`, set by `PSync::on_connection`. Reserved for the
+    /// EOF-delimited RDB streaming framing real Redis uses for diskless
+    /// replication once this codebase's `DataType::Rdb` supports more than
+    /// one wire encoding.
+    pub eof_framing: bool,
+}
+
+impl ReplicaConnection {
+    pub fn new(stream: WriteHandle, peer_address: String) -> ReplicaConnection {
+        ReplicaConnection {
+            stream,
+            peer_address,
+            acked_offset: 0,
+            eof_framing: false,
+        }
+    }
+}
+
+/// A client connection tracked while it has at least one active Pub/Sub
+/// subscription, together with the channels and glob patterns it's
+/// subscribed to. `stream` shares the same `WriteHandle` rationale as
+/// `ReplicaConnection::stream`.
+pub struct PubSubConnection {
+    pub stream: WriteHandle,
+    pub peer_address: String,
+    pub channels: HashSet<String>,
+    pub patterns: HashSet<String>,
+}
+
+impl PubSubConnection {
+    pub fn new(stream: WriteHandle, peer_address: String) -> PubSubConnection {
+        PubSubConnection {
+            stream,
+            peer_address,
+            channels: HashSet::new(),
+            patterns: HashSet::new(),
+        }
+    }
+}
 
 pub struct ServerState {
     pub port: usize,
     pub replica_of: Option<String>,
     pub master_replication_id: Option<String>,
     pub master_replication_offset: Option<usize>,
-    pub replica_connections: Arc<Mutex<Vec<TcpStream>>>
+    pub replica_connections: Arc<Mutex<Vec<ReplicaConnection>>>,
+    /// Fans propagated command bytes out to replicas served by
+    /// `async_runtime`. Unlike `replica_connections`, sending here never
+    /// blocks on a slow reader: `tokio::sync::broadcast` drops a lagging
+    /// subscriber's oldest unread messages instead of stalling the sender,
+    /// so one slow replica can't hold back propagation to the rest.
+    pub replica_broadcast: tokio::sync::broadcast::Sender<Vec<u8>>,
+    pub replication_backlog: Arc<Mutex<ReplicationBacklog>>,
+    /// Client connections with at least one `SUBSCRIBE`/`PSUBSCRIBE`
+    /// subscription, keyed implicitly by peer address (see
+    /// `register_pubsub_connection`). `PUBLISH` walks this list the same way
+    /// `broadcast_to_replicas` walks `replica_connections`.
+    pub pubsub_connections: Arc<Mutex<Vec<PubSubConnection>>>,
+    /// `REPLCONF capa` tokens advertised by each connection so far, keyed by
+    /// peer address. Populated before `PSYNC` arrives on the same
+    /// connection, so `PSync::execute` can look a replica's negotiated
+    /// capabilities up by `peer_address` to decide how to frame its reply.
+    pub advertised_capabilities: Arc<Mutex<HashMap<String, HashSet<String>>>>,
+    pub aof_writer: Arc<Mutex<Option<AofWriter>>>,
+    pub config: Arc<Mutex<ServerConfig>>,
+    /// This node's id in the cluster mesh, gossiped to peers so they can key
+    /// their own `PeerTable` by it rather than by address.
+    pub node_id: String,
+    pub peer_table: Arc<Mutex<PeerTable>>,
+    /// This node's handshake identity, used to authenticate secure
+    /// connections this node initiates or accepts (see
+    /// `secure_transport::negotiate_client`/`negotiate_server`).
+    pub node_identity: NodeIdentity,
 }
 
 impl ServerState {
 
     const REPLICATION_ID_LENGTH: usize = 20;
 
+    // 1 MiB, matching the default `repl-backlog-size` Redis ships with.
+    const REPLICATION_BACKLOG_CAPACITY: usize = 1024 * 1024;
+
+    // Same length as a replication id; cluster node ids follow the same
+    // 40-hex-character convention `redis-server` uses.
+    const NODE_ID_LENGTH: usize = 20;
+
+    // Bounds how many propagated commands a lagging `async_runtime` replica
+    // can fall behind by before `tokio::sync::broadcast` starts dropping the
+    // oldest ones for it.
+    const REPLICA_BROADCAST_CAPACITY: usize = 1024;
+
     pub fn get_replica_of_address(&self) -> Result<Option<String>, anyhow::Error> {
         match &self.replica_of {
             Some(replica_of) => {
@@ -33,14 +188,35 @@ impl ServerState {
         }
     }
 
-    fn generate_replication_id() -> String {
+    fn generate_hex_id(byte_length: usize) -> String {
         let mut generator = rand::thread_rng();
-        let random_bytes: Vec<u8> = (0..ServerState::REPLICATION_ID_LENGTH).map(|_| generator.gen()).collect();
-        let formatted_bytes: String = random_bytes.iter().map(|x| format!("{:02x}", x)).collect();
-        formatted_bytes
+        let random_bytes: Vec<u8> = (0..byte_length).map(|_| generator.gen()).collect();
+        random_bytes.iter().map(|x| format!("{:02x}", x)).collect()
     }
 
-    pub fn new<'a>(replica_of: Option<String>, port: usize) -> ServerState {
+    fn generate_replication_id() -> String {
+        ServerState::generate_hex_id(ServerState::REPLICATION_ID_LENGTH)
+    }
+
+    fn generate_node_id() -> String {
+        ServerState::generate_hex_id(ServerState::NODE_ID_LENGTH)
+    }
+
+    pub fn new(replica_of: Option<String>, port: usize) -> ServerState {
+        let config = ServerConfig { port, replica_of, ..ServerConfig::default() };
+        ServerState::new_with_config(config)
+    }
+
+    /// Builds a `ServerState` from an already-parsed `ServerConfig`, e.g. one
+    /// loaded from a `redis.conf` file and command-line overrides at startup.
+    pub fn new_with_config(config: ServerConfig) -> ServerState {
+        let port = config.port;
+        let replica_of = config.replica_of.clone();
+        let node_id = ServerState::generate_node_id();
+        let peer_table = Arc::new(Mutex::new(PeerTable::new(node_id.clone(), format!("127.0.0.1:{}", port))));
+        let config = Arc::new(Mutex::new(config));
+        let node_identity = NodeIdentity::generate();
+        let (replica_broadcast, _) = tokio::sync::broadcast::channel(ServerState::REPLICA_BROADCAST_CAPACITY);
         match replica_of {
             Some(replica_of) =>
                 ServerState {
@@ -48,7 +224,16 @@ impl ServerState {
                     replica_of: Some(replica_of),
                     master_replication_id: None,
                     master_replication_offset: None,
-                    replica_connections: Arc::new(Mutex::new(Vec::new()))
+                    replica_connections: Arc::new(Mutex::new(Vec::new())),
+                    replica_broadcast,
+                    replication_backlog: Arc::new(Mutex::new(ReplicationBacklog::new(ServerState::REPLICATION_BACKLOG_CAPACITY))),
+                    pubsub_connections: Arc::new(Mutex::new(Vec::new())),
+                    advertised_capabilities: Arc::new(Mutex::new(HashMap::new())),
+                    aof_writer: Arc::new(Mutex::new(None)),
+                    config,
+                    node_id,
+                    peer_table,
+                    node_identity
                 },
             None =>
                 ServerState {
@@ -56,10 +241,239 @@ impl ServerState {
                     replica_of: None,
                     master_replication_id: Some(ServerState::generate_replication_id()),
                     master_replication_offset: Some(0),
-                    replica_connections: Arc::new(Mutex::new(Vec::new()))
+                    replica_connections: Arc::new(Mutex::new(Vec::new())),
+                    replica_broadcast,
+                    replication_backlog: Arc::new(Mutex::new(ReplicationBacklog::new(ServerState::REPLICATION_BACKLOG_CAPACITY))),
+                    pubsub_connections: Arc::new(Mutex::new(Vec::new())),
+                    advertised_capabilities: Arc::new(Mutex::new(HashMap::new())),
+                    aof_writer: Arc::new(Mutex::new(None)),
+                    config,
+                    node_id,
+                    peer_table,
+                    node_identity
                 }
         }
     }
+
+    /// Records bytes that were just propagated to replicas: appends them to
+    /// the replication backlog, which advances the master's replication
+    /// offset by their exact length, as Redis does for every propagated
+    /// command.
+    pub fn record_propagated_bytes(&self, bytes: &[u8]) -> Result<(), anyhow::Error> {
+        let mut backlog = self.replication_backlog.lock().map_err(|e| anyhow::anyhow!("Failed to lock replication backlog: {}", e))?;
+        backlog.append(bytes);
+        Ok(())
+    }
+
+    /// Returns the master's current replication offset, i.e. the offset of
+    /// the most recently propagated byte.
+    pub fn current_replication_offset(&self) -> Result<usize, anyhow::Error> {
+        let backlog = self.replication_backlog.lock().map_err(|e| anyhow::anyhow!("Failed to lock replication backlog: {}", e))?;
+        Ok(backlog.current_offset())
+    }
+
+    /// Records the offset a replica has acknowledged via `REPLCONF ACK`, so
+    /// `WAIT` can count how many replicas are caught up to a given offset.
+    pub fn record_replica_ack(&self, replica_address: &str, acked_offset: usize) -> Result<(), anyhow::Error> {
+        let mut replicas = self.replica_connections.lock().map_err(|e| anyhow::anyhow!("Failed to lock replica connections: {}", e))?;
+        for replica in replicas.iter_mut() {
+            if replica.peer_address == replica_address {
+                replica.acked_offset = acked_offset;
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts how many connected replicas have acknowledged at least `target_offset`.
+    pub fn replicas_caught_up_to(&self, target_offset: usize) -> Result<usize, anyhow::Error> {
+        let replicas = self.replica_connections.lock().map_err(|e| anyhow::anyhow!("Failed to lock replica connections: {}", e))?;
+        Ok(replicas.iter().filter(|replica| replica.acked_offset >= target_offset).count())
+    }
+
+    /// Writes `bytes` to every connected replica, used by `WAIT` to send
+    /// `REPLCONF GETACK *` without going through normal command propagation.
+    pub fn broadcast_to_replicas(&self, bytes: &[u8]) -> Result<(), anyhow::Error> {
+        let mut replicas = self.replica_connections.lock().map_err(|e| anyhow::anyhow!("Failed to lock replica connections: {}", e))?;
+        for replica in replicas.iter_mut() {
+            replica.stream.lock().map_err(|e| anyhow::anyhow!("Failed to lock replica stream: {}", e))?.write_all(bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Fans `bytes` out to every replica served by `async_runtime` over
+    /// `replica_broadcast`. `Sender::send` only errors when no receiver is
+    /// currently subscribed, which just means no async replica is connected
+    /// right now - nothing to propagate to, so it's not treated as a failure.
+    pub fn broadcast_propagated_bytes_async(&self, bytes: &[u8]) {
+        let _ = self.replica_broadcast.send(bytes.to_vec());
+    }
+
+    pub fn is_master(&self) -> bool {
+        self.replica_of.is_none()
+    }
+
+    /// Installs the AOF writer used to persist propagated write commands,
+    /// enabling AOF durability for the lifetime of the server.
+    pub fn enable_aof(&self, writer: AofWriter) -> Result<(), anyhow::Error> {
+        let mut aof_writer = self.aof_writer.lock().map_err(|e| anyhow::anyhow!("Failed to lock AOF writer: {}", e))?;
+        *aof_writer = Some(writer);
+        Ok(())
+    }
+
+    /// Appends `bytes` to the AOF log if AOF persistence is enabled; a no-op
+    /// otherwise.
+    pub fn append_to_aof(&self, bytes: &[u8]) -> Result<(), anyhow::Error> {
+        let mut aof_writer = self.aof_writer.lock().map_err(|e| anyhow::anyhow!("Failed to lock AOF writer: {}", e))?;
+        if let Some(writer) = aof_writer.as_mut() {
+            writer.append(bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Compacts the AOF log into one `SET` per string key of `storage` (keys
+    /// holding aggregate values are not written; see `AofWriter::rewrite`),
+    /// as triggered by `BGREWRITEAOF`. Returns `false` without doing anything
+    /// if AOF persistence isn't enabled.
+    pub fn rewrite_aof(&self, storage: &storage::Storage) -> Result<bool, anyhow::Error> {
+        let mut aof_writer = self.aof_writer.lock().map_err(|e| anyhow::anyhow!("Failed to lock AOF writer: {}", e))?;
+        match aof_writer.as_mut() {
+            Some(writer) => {
+                writer.rewrite(storage)?;
+                Ok(true)
+            },
+            None => Ok(false)
+        }
+    }
+
+    /// Registers `stream`'s connection in the Pub/Sub connection table if
+    /// it isn't there already, called from `SUBSCRIBE`/`PSUBSCRIBE`'s
+    /// `on_connection` hook the same way `PSYNC` registers a replica link.
+    pub fn register_pubsub_connection(&self, stream: WriteHandle, peer_address: String) -> Result<(), anyhow::Error> {
+        let mut connections = self.pubsub_connections.lock().map_err(|e| anyhow::anyhow!("Failed to lock pubsub connections: {}", e))?;
+        let already_registered = connections.iter().any(|connection| connection.peer_address == peer_address);
+        if !already_registered {
+            connections.push(PubSubConnection::new(stream, peer_address));
+        }
+        Ok(())
+    }
+
+    /// Records a `REPLCONF capa <token>` advertisement against the
+    /// connection it arrived on.
+    pub fn record_advertised_capability(&self, peer_address: &str, capability: &str) -> Result<(), anyhow::Error> {
+        let mut capabilities = self.advertised_capabilities.lock().map_err(|e| anyhow::anyhow!("Failed to lock advertised capabilities: {}", e))?;
+        capabilities.entry(peer_address.to_owned()).or_insert_with(HashSet::new).insert(capability.to_owned());
+        Ok(())
+    }
+
+    /// Returns the capability tokens `peer_address` has advertised via
+    /// `REPLCONF capa` so far, or an empty set if it hasn't advertised any.
+    pub fn advertised_capabilities(&self, peer_address: &str) -> Result<HashSet<String>, anyhow::Error> {
+        let capabilities = self.advertised_capabilities.lock().map_err(|e| anyhow::anyhow!("Failed to lock advertised capabilities: {}", e))?;
+        Ok(capabilities.get(peer_address).cloned().unwrap_or_default())
+    }
+
+    fn with_pubsub_connection<T>(
+        &self,
+        peer_address: &str,
+        default: T,
+        apply: impl FnOnce(&mut PubSubConnection) -> T,
+    ) -> Result<T, anyhow::Error> {
+        let mut connections = self.pubsub_connections.lock().map_err(|e| anyhow::anyhow!("Failed to lock pubsub connections: {}", e))?;
+        let connection = connections.iter_mut().find(|connection| connection.peer_address == peer_address);
+        Ok(match connection {
+            Some(connection) => apply(connection),
+            None => default,
+        })
+    }
+
+    /// Subscribes `peer_address` to `channel`, returning its total
+    /// channel-plus-pattern subscription count afterward.
+    pub fn subscribe(&self, peer_address: &str, channel: &str) -> Result<usize, anyhow::Error> {
+        self.with_pubsub_connection(peer_address, 0, |connection| {
+            connection.channels.insert(channel.to_owned());
+            connection.channels.len() + connection.patterns.len()
+        })
+    }
+
+    /// Unsubscribes `peer_address` from `channel`, returning its remaining
+    /// channel-plus-pattern subscription count.
+    pub fn unsubscribe(&self, peer_address: &str, channel: &str) -> Result<usize, anyhow::Error> {
+        self.with_pubsub_connection(peer_address, 0, |connection| {
+            connection.channels.remove(channel);
+            connection.channels.len() + connection.patterns.len()
+        })
+    }
+
+    /// Subscribes `peer_address` to `pattern`, returning its total
+    /// channel-plus-pattern subscription count afterward.
+    pub fn psubscribe(&self, peer_address: &str, pattern: &str) -> Result<usize, anyhow::Error> {
+        self.with_pubsub_connection(peer_address, 0, |connection| {
+            connection.patterns.insert(pattern.to_owned());
+            connection.channels.len() + connection.patterns.len()
+        })
+    }
+
+    /// Unsubscribes `peer_address` from `pattern`, returning its remaining
+    /// channel-plus-pattern subscription count.
+    pub fn punsubscribe(&self, peer_address: &str, pattern: &str) -> Result<usize, anyhow::Error> {
+        self.with_pubsub_connection(peer_address, 0, |connection| {
+            connection.patterns.remove(pattern);
+            connection.channels.len() + connection.patterns.len()
+        })
+    }
+
+    /// Returns the channels `peer_address` is currently subscribed to,
+    /// used by a bare `UNSUBSCRIBE` (no arguments) to mean "all of them".
+    pub fn subscribed_channels(&self, peer_address: &str) -> Result<Vec<String>, anyhow::Error> {
+        self.with_pubsub_connection(peer_address, Vec::new(), |connection| {
+            connection.channels.iter().cloned().collect()
+        })
+    }
+
+    /// Returns the patterns `peer_address` is currently subscribed to, used
+    /// by a bare `PUNSUBSCRIBE` the same way.
+    pub fn subscribed_patterns(&self, peer_address: &str) -> Result<Vec<String>, anyhow::Error> {
+        self.with_pubsub_connection(peer_address, Vec::new(), |connection| {
+            connection.patterns.iter().cloned().collect()
+        })
+    }
+
+    /// Delivers `message` on `channel` to every subscribed connection: a
+    /// `message` push to connections subscribed to `channel` directly, and a
+    /// `pmessage` push to connections whose pattern matches it. Returns how
+    /// many pushes were delivered.
+    pub fn publish(&self, channel: &str, message: &str) -> Result<usize, anyhow::Error> {
+        let mut connections = self.pubsub_connections.lock().map_err(|e| anyhow::anyhow!("Failed to lock pubsub connections: {}", e))?;
+        let mut delivered = 0;
+        for connection in connections.iter_mut() {
+            if connection.channels.contains(channel) {
+                let push = protocol::push(vec![
+                    protocol::bulk_string("message"),
+                    protocol::bulk_string(channel),
+                    protocol::bulk_string(message),
+                ]);
+                let wrote = connection.stream.lock().map(|mut stream| stream.write_all(&push.serialize()).is_ok()).unwrap_or(false);
+                if wrote {
+                    delivered += 1;
+                }
+            }
+            for pattern in connection.patterns.iter() {
+                if config::glob_match(pattern, channel) {
+                    let push = protocol::push(vec![
+                        protocol::bulk_string("pmessage"),
+                        protocol::bulk_string(pattern),
+                        protocol::bulk_string(channel),
+                        protocol::bulk_string(message),
+                    ]);
+                    let wrote = connection.stream.lock().map(|mut stream| stream.write_all(&push.serialize()).is_ok()).unwrap_or(false);
+                    if wrote {
+                        delivered += 1;
+                    }
+                }
+            }
+        }
+        Ok(delivered)
+    }
 }
 
 #[cfg(test)]
@@ -75,6 +489,14 @@ mod tests {
         assert_eq!(state.master_replication_id.map(|x| x.len()).unwrap_or(0), 40);
     }
 
+    #[test]
+    fn should_assign_a_node_id_and_seed_the_peer_table_with_its_own_address() {
+        let state = ServerState::new(None, 1234);
+        assert_eq!(state.node_id.len(), 40);
+        assert_eq!(state.peer_table.lock().unwrap().peer_ids().len(), 0);
+        assert_eq!(state.peer_table.lock().unwrap().gossip_entries(), vec![(state.node_id.clone(), "127.0.0.1:1234".to_owned())]);
+    }
+
     #[test]
     fn should_set_replication_id_and_offset_for_slave() {
         let state = ServerState::new(Some("localhost 6379".to_owned()), 1234);
@@ -83,5 +505,126 @@ mod tests {
         assert_eq!(state.master_replication_offset, None);
         assert_eq!(state.master_replication_id, None);
     }
+
+    #[test]
+    fn should_append_to_backlog_and_advance_offset() {
+        let mut backlog = ReplicationBacklog::new(1024);
+        backlog.append(b"hello");
+        backlog.append(b"world");
+        assert_eq!(backlog.current_offset(), 10);
+        assert_eq!(backlog.start_offset(), 0);
+        assert_eq!(backlog.slice_from(0), Some(b"helloworld".to_vec()));
+        assert_eq!(backlog.slice_from(5), Some(b"world".to_vec()));
+    }
+
+    #[test]
+    fn should_evict_oldest_bytes_once_over_capacity() {
+        let mut backlog = ReplicationBacklog::new(4);
+        backlog.append(b"ab");
+        backlog.append(b"cdef");
+        // Capacity is 4, so only the last 4 bytes ("cdef") are retained.
+        assert_eq!(backlog.start_offset(), 2);
+        assert_eq!(backlog.current_offset(), 6);
+        assert_eq!(backlog.slice_from(2), Some(b"cdef".to_vec()));
+        assert_eq!(backlog.slice_from(0), None);
+    }
+
+    #[test]
+    fn should_record_propagated_bytes_on_server_state() {
+        let state = ServerState::new(None, 6379);
+        state.record_propagated_bytes(b"*1\r\n$4\r\nPING\r\n").unwrap();
+        assert_eq!(state.current_replication_offset().unwrap(), 14);
+    }
+
+    #[test]
+    fn should_track_subscription_counts_across_channels_and_patterns() {
+        use std::net::{TcpListener, TcpStream};
+        use crate::secure_transport;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server_side, _) = listener.accept().unwrap();
+        let peer_address = server_side.peer_addr().unwrap().to_string();
+
+        let state = ServerState::new(None, 6379);
+        let handle = secure_transport::plain_handle(server_side);
+        state.register_pubsub_connection(Arc::clone(&handle), peer_address.clone()).unwrap();
+
+        assert_eq!(state.subscribe(&peer_address, "news").unwrap(), 1);
+        assert_eq!(state.psubscribe(&peer_address, "eve*").unwrap(), 2);
+        assert_eq!(state.subscribed_channels(&peer_address).unwrap(), vec!["news".to_owned()]);
+        assert_eq!(state.subscribed_patterns(&peer_address).unwrap(), vec!["eve*".to_owned()]);
+
+        assert_eq!(state.unsubscribe(&peer_address, "news").unwrap(), 1);
+        assert_eq!(state.punsubscribe(&peer_address, "eve*").unwrap(), 0);
+
+        // Registering the same peer again must not create a second entry.
+        state.register_pubsub_connection(Arc::clone(&handle), peer_address).unwrap();
+        assert_eq!(state.pubsub_connections.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn should_deliver_publish_to_matching_channel_and_pattern_subscribers_only() {
+        use std::io::Read;
+        use std::net::{TcpListener, TcpStream};
+        use crate::secure_transport;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server_side, _) = listener.accept().unwrap();
+        let peer_address = server_side.peer_addr().unwrap().to_string();
+
+        let state = ServerState::new(None, 6379);
+        state.register_pubsub_connection(secure_transport::plain_handle(server_side), peer_address.clone()).unwrap();
+        state.subscribe(&peer_address, "news").unwrap();
+        state.psubscribe(&peer_address, "eve*").unwrap();
+
+        assert_eq!(state.publish("news", "hello").unwrap(), 1);
+        assert_eq!(state.publish("events", "starting").unwrap(), 1);
+        assert_eq!(state.publish("unrelated", "ignored").unwrap(), 0);
+
+        let mut received = vec![0u8; 1024];
+        let read_bytes = client.read(&mut received).unwrap();
+        let received = String::from_utf8_lossy(&received[0..read_bytes]);
+        assert!(received.contains("message") && received.contains("news") && received.contains("hello"));
+        assert!(received.contains("pmessage") && received.contains("eve*") && received.contains("events"));
+    }
+
+    #[test]
+    fn should_rewrite_aof_only_when_enabled() {
+        use crate::aof::{AofWriter, FsyncPolicy};
+        use std::collections::HashMap;
+
+        let state = ServerState::new(None, 6379);
+        let empty_storage = storage::Storage::new(HashMap::new());
+        assert_eq!(state.rewrite_aof(&empty_storage).unwrap(), false);
+
+        let path = std::env::temp_dir().join("redis_server_state_test.aof");
+        let _ = std::fs::remove_file(&path);
+        state.enable_aof(AofWriter::open(&path, FsyncPolicy::Always).unwrap()).unwrap();
+        assert_eq!(state.rewrite_aof(&empty_storage).unwrap(), true);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn should_record_and_retrieve_advertised_capabilities_per_peer_address() {
+        let state = ServerState::new(None, 6379);
+
+        assert!(state.advertised_capabilities("127.0.0.1:9999").unwrap().is_empty());
+
+        state.record_advertised_capability("127.0.0.1:9999", "eof").unwrap();
+        state.record_advertised_capability("127.0.0.1:9999", "psync2").unwrap();
+        state.record_advertised_capability("127.0.0.1:8888", "psync2").unwrap();
+
+        assert_eq!(
+            state.advertised_capabilities("127.0.0.1:9999").unwrap(),
+            HashSet::from(["eof".to_owned(), "psync2".to_owned()])
+        );
+        assert_eq!(
+            state.advertised_capabilities("127.0.0.1:8888").unwrap(),
+            HashSet::from(["psync2".to_owned()])
+        );
+    }
 }
 
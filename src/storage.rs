@@ -6,7 +6,28 @@ use crate::rdb;
 
 #[derive(Debug, PartialEq)]
 pub struct Storage {
-    pub data: HashMap<String, StoredValue>
+    pub data: HashMap<Vec<u8>, StoredValue>
+}
+
+/// The value held by a key. Most commands (GET/SET) only ever deal with
+/// `String`, but the RDB codec needs to round-trip the aggregate types too.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(Vec<u8>),
+    List(Vec<Vec<u8>>),
+    Set(Vec<Vec<u8>>),
+    Hash(Vec<(Vec<u8>, Vec<u8>)>),
+    SortedSet(Vec<(Vec<u8>, f64)>),
+}
+
+impl Value {
+    /// Returns the raw bytes if this is a `String` value, `None` otherwise.
+    pub fn as_bytes(&self) -> Option<&Vec<u8>> {
+        match self {
+            Value::String(bytes) => Some(bytes),
+            _ => None
+        }
+    }
 }
 
 impl Storage {
@@ -23,26 +44,61 @@ impl Storage {
         rdb::from_rdb(&mut reader)
     }
 
-    pub fn new(data: HashMap<String, StoredValue>) -> Storage {
+    pub fn new(data: HashMap<Vec<u8>, StoredValue>) -> Storage {
         Storage {
             data
         }
     }
 
-    pub fn to_pairs(&self) -> HashMap<String, Vec<u8>> {
+    /// Pairs for every key currently holding a `String` value. Aggregate
+    /// types (list/set/hash/sorted set) are not represented by this API yet.
+    pub fn to_pairs(&self) -> HashMap<Vec<u8>, Vec<u8>> {
         let mut result = HashMap::new();
         for (key, value) in self.data.iter() {
-            result.insert(key.clone(), value.value.clone());
+            if let Some(bytes) = value.value.as_bytes() {
+                result.insert(key.clone(), bytes.clone());
+            }
         }
         result
     }
 
-    pub fn set(&mut self, key: &str, value: Vec<u8>, expires_in_ms: Option<u64>) -> Result<Option<StoredValue>, anyhow::Error> {
-        Ok(self.data.insert(key.to_owned(), StoredValue::from(value, expires_in_ms)?))
+    /// Keys and values are binary-safe: a key or value containing arbitrary
+    /// bytes (including invalid UTF-8) round-trips unchanged.
+    pub fn set(&mut self, key: &[u8], value: Vec<u8>, expires_in_ms: Option<u64>) -> Result<Option<StoredValue>, anyhow::Error> {
+        Ok(self.data.insert(key.to_vec(), StoredValue::from(value, expires_in_ms)?))
+    }
+
+    /// Like `set`, but `expires_at_ms` is an absolute Unix-epoch millisecond
+    /// timestamp rather than a duration from now. Used by SET's EXAT/PXAT
+    /// options.
+    pub fn set_at(&mut self, key: &[u8], value: Vec<u8>, expires_at_ms: Option<u128>) -> Result<Option<StoredValue>, anyhow::Error> {
+        let expires_in_ms = match expires_at_ms {
+            Some(expires_at_ms) => {
+                let current_time_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+                Some(expires_at_ms.saturating_sub(current_time_ms) as u64)
+            }
+            None => None,
+        };
+        self.set(key, value, expires_in_ms)
+    }
+
+    /// Remaining TTL for `key` if it currently holds a live value, or `None`
+    /// if the key is absent, expired, or has no expiry set. Used by SET's
+    /// KEEPTTL option to carry an existing expiry forward across an
+    /// overwrite.
+    pub fn remaining_ttl_ms(&self, key: &[u8]) -> Result<Option<u64>, anyhow::Error> {
+        match self.data.get(key) {
+            // `StoredValue::remaining_ttl_ms` reports an expired value as `Some(0)`.
+            Some(stored_value) => match stored_value.remaining_ttl_ms()? {
+                Some(0) => Ok(None),
+                remaining_ttl_ms => Ok(remaining_ttl_ms),
+            },
+            None => Ok(None),
+        }
     }
 
-    pub fn get(&mut self, key: &str) -> Result<Option<Vec<u8>>, anyhow::Error> {
-        let value = match self.data.get(&key.to_owned()) {
+    pub fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, anyhow::Error> {
+        let value = match self.data.get(key) {
             Some(stored_value) => {
               let current_time_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
               let has_value_expired = if let Some(expires_in_ms) = stored_value.expires_in_ms {
@@ -53,7 +109,7 @@ impl Storage {
               if has_value_expired {
                   None
               } else {
-                  Some(stored_value.value.clone())
+                  stored_value.value.as_bytes().cloned()
               }
             },
             None => None
@@ -66,15 +122,41 @@ impl Storage {
 pub struct StoredValue {
     expires_in_ms: Option<u64>,
     last_modified_timestamp: u128,
-    pub value: Vec<u8>
+    pub value: Value
 }
 
 impl StoredValue {
     pub fn from(value: Vec<u8>, expires_in_ms: Option<u64>) -> Result<StoredValue, anyhow::Error> {
+        StoredValue::from_value(Value::String(value), expires_in_ms)
+    }
+
+    /// Like `from`, but for the aggregate types the RDB codec can load
+    /// (list/set/hash/sorted set) rather than a plain string.
+    pub fn from_value(value: Value, expires_in_ms: Option<u64>) -> Result<StoredValue, anyhow::Error> {
         Ok(StoredValue {
             expires_in_ms,
             last_modified_timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis(),
             value
         })
     }
+
+    /// Returns the milliseconds remaining until this value expires, `Some(0)`
+    /// if it has already expired, or `None` if it has no expiry set.
+    pub fn remaining_ttl_ms(&self) -> Result<Option<u64>, anyhow::Error> {
+        match self.expires_in_ms {
+            None => Ok(None),
+            Some(expires_in_ms) => {
+                let current_time_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+                let expires_at_ms = self.last_modified_timestamp + expires_in_ms as u128;
+                Ok(Some(expires_at_ms.saturating_sub(current_time_ms) as u64))
+            }
+        }
+    }
+
+    /// Returns the absolute expiry time in milliseconds since the Unix epoch,
+    /// or `None` if this value has no expiry set. Used by the RDB writer to
+    /// emit the `0xFC` expiry opcode.
+    pub fn expires_at_ms(&self) -> Option<u128> {
+        self.expires_in_ms.map(|expires_in_ms| self.last_modified_timestamp + expires_in_ms as u128)
+    }
 }
\ No newline at end of file
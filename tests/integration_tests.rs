@@ -12,25 +12,25 @@ use codecrafters_redis::protocol;
 use codecrafters_redis::storage::{Storage, StoredValue};
 use codecrafters_redis::server_state::ServerState;
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use std::thread;
+use std::sync::Arc;
+use tokio::sync::Mutex;
 use std::time::Duration;
 use std::error::Error;
 
 fn create_test_storage() -> Arc<Mutex<Storage>> {
-    let data: HashMap<String, StoredValue> = HashMap::new();
+    let data: HashMap<Vec<u8>, StoredValue> = HashMap::new();
     Arc::new(Mutex::new(Storage::new(data)))
 }
 
 // ============= PING TESTS =============
 
-#[test]
-fn e2e_ping_works() -> Result<(), Box<dyn Error>> {
+#[tokio::test]
+async fn e2e_ping_works() -> Result<(), Box<dyn Error>> {
     let message = protocol::array(vec![protocol::bulk_string("PING")]);
     let cmd = Ping { message: &message };
 
     let storage = create_test_storage();
-    let result = cmd.execute(&storage)?;
+    let result = cmd.execute(&storage).await?;
 
     assert_eq!(result[0].as_string()?, "PONG");
     Ok(())
@@ -38,8 +38,8 @@ fn e2e_ping_works() -> Result<(), Box<dyn Error>> {
 
 // ============= ECHO TESTS =============
 
-#[test]
-fn e2e_echo_returns_argument() -> Result<(), Box<dyn Error>> {
+#[tokio::test]
+async fn e2e_echo_returns_argument() -> Result<(), Box<dyn Error>> {
     let echo_msg = protocol::bulk_string("Hello Redis!");
     let message = protocol::array(vec![
         protocol::bulk_string("ECHO"),
@@ -56,7 +56,7 @@ fn e2e_echo_returns_argument() -> Result<(), Box<dyn Error>> {
     };
 
     let storage = create_test_storage();
-    let result = cmd.execute(&storage)?;
+    let result = cmd.execute(&storage).await?;
 
     assert_eq!(result[0].as_string()?, "Hello Redis!");
     Ok(())
@@ -64,8 +64,8 @@ fn e2e_echo_returns_argument() -> Result<(), Box<dyn Error>> {
 
 // ============= SET/GET TESTS =============
 
-#[test]
-fn e2e_set_get_basic() -> Result<(), Box<dyn Error>> {
+#[tokio::test]
+async fn e2e_set_get_basic() -> Result<(), Box<dyn Error>> {
     let storage = create_test_storage();
 
     // Set a value
@@ -75,7 +75,7 @@ fn e2e_set_get_basic() -> Result<(), Box<dyn Error>> {
         protocol::bulk_string("alice"),
     ]);
     let set_cmd = Set { message: &set_msg };
-    let set_result = set_cmd.execute(&storage)?;
+    let set_result = set_cmd.execute(&storage).await?;
     assert_eq!(set_result[0].as_string()?, "OK");
 
     // Get the value
@@ -84,13 +84,13 @@ fn e2e_set_get_basic() -> Result<(), Box<dyn Error>> {
         protocol::bulk_string("username"),
     ]);
     let get_cmd = Get { message: &get_msg };
-    let get_result = get_cmd.execute(&storage)?;
+    let get_result = get_cmd.execute(&storage).await?;
     assert_eq!(get_result[0].as_string()?, "alice");
     Ok(())
 }
 
-#[test]
-fn e2e_multiple_keys() -> Result<(), Box<dyn Error>> {
+#[tokio::test]
+async fn e2e_multiple_keys() -> Result<(), Box<dyn Error>> {
     let storage = create_test_storage();
 
     // Set multiple values
@@ -108,7 +108,7 @@ fn e2e_multiple_keys() -> Result<(), Box<dyn Error>> {
             protocol::bulk_string(value),
         ]);
         let cmd = Set { message: &msg };
-        cmd.execute(&storage)?;
+        cmd.execute(&storage).await?;
     }
 
     // Verify all values
@@ -118,14 +118,14 @@ fn e2e_multiple_keys() -> Result<(), Box<dyn Error>> {
             protocol::bulk_string(key),
         ]);
         let cmd = Get { message: &msg };
-        let result = cmd.execute(&storage)?;
+        let result = cmd.execute(&storage).await?;
         assert_eq!(result[0].as_string()?, *expected_value);
     }
     Ok(())
 }
 
-#[test]
-fn e2e_get_nonexistent_key() -> Result<(), Box<dyn Error>> {
+#[tokio::test]
+async fn e2e_get_nonexistent_key() -> Result<(), Box<dyn Error>> {
     let storage = create_test_storage();
 
     let msg = protocol::array(vec![
@@ -133,15 +133,15 @@ fn e2e_get_nonexistent_key() -> Result<(), Box<dyn Error>> {
         protocol::bulk_string("does_not_exist"),
     ]);
     let cmd = Get { message: &msg };
-    let result = cmd.execute(&storage)?;
+    let result = cmd.execute(&storage).await?;
 
     // Should return empty bulk string
     assert_eq!(result[0].as_string()?, "");
     Ok(())
 }
 
-#[test]
-fn e2e_overwrite_key() -> Result<(), Box<dyn Error>> {
+#[tokio::test]
+async fn e2e_overwrite_key() -> Result<(), Box<dyn Error>> {
     let storage = create_test_storage();
 
     // Set initial value
@@ -150,14 +150,14 @@ fn e2e_overwrite_key() -> Result<(), Box<dyn Error>> {
         protocol::bulk_string("counter"),
         protocol::bulk_string("10"),
     ]);
-    Set { message: &msg1 }.execute(&storage)?;
+    Set { message: &msg1 }.execute(&storage).await?;
 
     // Get it
     let msg2 = protocol::array(vec![
         protocol::bulk_string("GET"),
         protocol::bulk_string("counter"),
     ]);
-    let result1 = Get { message: &msg2 }.execute(&storage)?;
+    let result1 = Get { message: &msg2 }.execute(&storage).await?;
     assert_eq!(result1[0].as_string()?, "10");
 
     // Overwrite it
@@ -166,18 +166,18 @@ fn e2e_overwrite_key() -> Result<(), Box<dyn Error>> {
         protocol::bulk_string("counter"),
         protocol::bulk_string("20"),
     ]);
-    Set { message: &msg3 }.execute(&storage)?;
+    Set { message: &msg3 }.execute(&storage).await?;
 
     // Get new value
-    let result2 = Get { message: &msg2 }.execute(&storage)?;
+    let result2 = Get { message: &msg2 }.execute(&storage).await?;
     assert_eq!(result2[0].as_string()?, "20");
     Ok(())
 }
 
 // ============= EXPIRATION TESTS =============
 
-#[test]
-fn e2e_key_expires() -> Result<(), Box<dyn Error>> {
+#[tokio::test]
+async fn e2e_key_expires() -> Result<(), Box<dyn Error>> {
     let storage = create_test_storage();
 
     // Set with 100ms expiration
@@ -188,27 +188,27 @@ fn e2e_key_expires() -> Result<(), Box<dyn Error>> {
         protocol::bulk_string("px"),
         protocol::bulk_string("100"),
     ]);
-    Set { message: &msg }.execute(&storage)?;
+    Set { message: &msg }.execute(&storage).await?;
 
     // Should exist immediately
     let get_msg = protocol::array(vec![
         protocol::bulk_string("GET"),
         protocol::bulk_string("temp_key"),
     ]);
-    let result1 = Get { message: &get_msg }.execute(&storage)?;
+    let result1 = Get { message: &get_msg }.execute(&storage).await?;
     assert_eq!(result1[0].as_string()?, "temp_value");
 
     // Wait for expiration
-    thread::sleep(Duration::from_millis(150));
+    tokio::time::sleep(Duration::from_millis(150)).await;
 
     // Should be gone now
-    let result2 = Get { message: &get_msg }.execute(&storage)?;
+    let result2 = Get { message: &get_msg }.execute(&storage).await?;
     assert_eq!(result2[0].as_string()?, "");
     Ok(())
 }
 
-#[test]
-fn e2e_key_expires_uppercase_px() -> Result<(), Box<dyn Error>> {
+#[tokio::test]
+async fn e2e_key_expires_uppercase_px() -> Result<(), Box<dyn Error>> {
     let storage = create_test_storage();
 
     // Set with 100ms expiration using uppercase PX (as sent by redis-cli)
@@ -219,27 +219,27 @@ fn e2e_key_expires_uppercase_px() -> Result<(), Box<dyn Error>> {
         protocol::bulk_string("PX"),
         protocol::bulk_string("100"),
     ]);
-    Set { message: &msg }.execute(&storage)?;
+    Set { message: &msg }.execute(&storage).await?;
 
     // Should exist immediately
     let get_msg = protocol::array(vec![
         protocol::bulk_string("GET"),
         protocol::bulk_string("blueberry"),
     ]);
-    let result1 = Get { message: &get_msg }.execute(&storage)?;
+    let result1 = Get { message: &get_msg }.execute(&storage).await?;
     assert_eq!(result1[0].as_string()?, "raspberry");
 
     // Wait for expiration
-    thread::sleep(Duration::from_millis(150));
+    tokio::time::sleep(Duration::from_millis(150)).await;
 
     // Should be gone now
-    let result2 = Get { message: &get_msg }.execute(&storage)?;
+    let result2 = Get { message: &get_msg }.execute(&storage).await?;
     assert_eq!(result2[0].as_string()?, "");
     Ok(())
 }
 
-#[test]
-fn e2e_long_lived_key() -> Result<(), Box<dyn Error>> {
+#[tokio::test]
+async fn e2e_long_lived_key() -> Result<(), Box<dyn Error>> {
     let storage = create_test_storage();
 
     // Set with 5 second expiration
@@ -250,30 +250,30 @@ fn e2e_long_lived_key() -> Result<(), Box<dyn Error>> {
         protocol::bulk_string("px"),
         protocol::bulk_string("5000"),
     ]);
-    Set { message: &msg }.execute(&storage)?;
+    Set { message: &msg }.execute(&storage).await?;
 
     // Should still exist after 100ms
-    thread::sleep(Duration::from_millis(100));
+    tokio::time::sleep(Duration::from_millis(100)).await;
     let get_msg = protocol::array(vec![
         protocol::bulk_string("GET"),
         protocol::bulk_string("session"),
     ]);
-    let result = Get { message: &get_msg }.execute(&storage)?;
+    let result = Get { message: &get_msg }.execute(&storage).await?;
     assert_eq!(result[0].as_string()?, "session_data");
     Ok(())
 }
 
 // ============= BINARY DATA TESTS =============
 
-#[test]
-fn e2e_binary_data_preserved() -> Result<(), Box<dyn Error>> {
+#[tokio::test]
+async fn e2e_binary_data_preserved() -> Result<(), Box<dyn Error>> {
     let storage = create_test_storage();
 
     // Manually insert binary data
     let binary_data = vec![0u8, 1, 2, 3, 255, 254, 127];
     {
-        let mut data = storage.lock().map_err(|_| "Failed to lock storage".to_string())?;
-        let _ = data.set("binary", binary_data.clone(), None);
+        let mut data = storage.lock().await;
+        let _ = data.set(b"binary", binary_data.clone(), None);
     }
 
     // Retrieve it
@@ -282,7 +282,7 @@ fn e2e_binary_data_preserved() -> Result<(), Box<dyn Error>> {
         protocol::bulk_string("binary"),
     ]);
     let cmd = Get { message: &msg };
-    let result = cmd.execute(&storage)?;
+    let result = cmd.execute(&storage).await?;
 
     match &result[0] {
         protocol::DataType::BulkString { value: Some(v) } => {
@@ -295,8 +295,8 @@ fn e2e_binary_data_preserved() -> Result<(), Box<dyn Error>> {
 
 // ============= ERROR HANDLING TESTS =============
 
-#[test]
-fn e2e_set_missing_value_fails() -> Result<(), Box<dyn Error>> {
+#[tokio::test]
+async fn e2e_set_missing_value_fails() -> Result<(), Box<dyn Error>> {
     let storage = create_test_storage();
 
     let msg = protocol::array(vec![
@@ -304,21 +304,21 @@ fn e2e_set_missing_value_fails() -> Result<(), Box<dyn Error>> {
         protocol::bulk_string("key_only"),
     ]);
     let cmd = Set { message: &msg };
-    let result = cmd.execute(&storage);
+    let result = cmd.execute(&storage).await;
 
     assert!(result.is_err());
     Ok(())
 }
 
-#[test]
-fn e2e_get_missing_key_fails() -> Result<(), Box<dyn Error>> {
+#[tokio::test]
+async fn e2e_get_missing_key_fails() -> Result<(), Box<dyn Error>> {
     let storage = create_test_storage();
 
     let msg = protocol::array(vec![
         protocol::bulk_string("GET"),
     ]);
     let cmd = Get { message: &msg };
-    let result = cmd.execute(&storage);
+    let result = cmd.execute(&storage).await;
 
     assert!(result.is_err());
     Ok(())
@@ -326,8 +326,8 @@ fn e2e_get_missing_key_fails() -> Result<(), Box<dyn Error>> {
 
 // ============= REPLICATION TESTS =============
 
-#[test]
-fn e2e_info_command_master() -> Result<(), Box<dyn Error>> {
+#[tokio::test]
+async fn e2e_info_command_master() -> Result<(), Box<dyn Error>> {
     let server_state = ServerState::new(None, 6379);
     let msg = protocol::array(vec![
         protocol::bulk_string("INFO"),
@@ -339,7 +339,7 @@ fn e2e_info_command_master() -> Result<(), Box<dyn Error>> {
     };
 
     let storage = create_test_storage();
-    let result = cmd.execute(&storage)?;
+    let result = cmd.execute(&storage).await?;
 
     let info = result[0].as_string()?;
     assert!(info.contains("role:master"));
@@ -348,8 +348,8 @@ fn e2e_info_command_master() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-#[test]
-fn e2e_info_command_replica() -> Result<(), Box<dyn Error>> {
+#[tokio::test]
+async fn e2e_info_command_replica() -> Result<(), Box<dyn Error>> {
     let server_state = ServerState::new(Some("localhost 6379".to_owned()), 6380);
     let msg = protocol::array(vec![
         protocol::bulk_string("INFO"),
@@ -361,7 +361,7 @@ fn e2e_info_command_replica() -> Result<(), Box<dyn Error>> {
     };
 
     let storage = create_test_storage();
-    let result = cmd.execute(&storage)?;
+    let result = cmd.execute(&storage).await?;
 
     let info = result[0].as_string()?;
     assert!(info.contains("role:slave"));
@@ -370,8 +370,8 @@ fn e2e_info_command_replica() -> Result<(), Box<dyn Error>> {
 
 // ============= COMPLEX SCENARIOS =============
 
-#[test]
-fn e2e_mixed_operations() -> Result<(), Box<dyn Error>> {
+#[tokio::test]
+async fn e2e_mixed_operations() -> Result<(), Box<dyn Error>> {
     let storage = create_test_storage();
 
     // 1. Set multiple cache entries
@@ -387,7 +387,7 @@ fn e2e_mixed_operations() -> Result<(), Box<dyn Error>> {
             protocol::bulk_string(key),
             protocol::bulk_string(value),
         ]);
-        Set { message: &msg }.execute(&storage)?;
+        Set { message: &msg }.execute(&storage).await?;
     }
 
     // 2. Get and verify
@@ -396,7 +396,7 @@ fn e2e_mixed_operations() -> Result<(), Box<dyn Error>> {
             protocol::bulk_string("GET"),
             protocol::bulk_string(key),
         ]);
-        let result = Get { message: &msg }.execute(&storage)?;
+        let result = Get { message: &msg }.execute(&storage).await?;
         assert_eq!(result[0].as_string()?, *expected_value);
     }
 
@@ -406,14 +406,14 @@ fn e2e_mixed_operations() -> Result<(), Box<dyn Error>> {
         protocol::bulk_string("cache:config:timeout"),
         protocol::bulk_string("60000"),
     ]);
-    Set { message: &msg }.execute(&storage)?;
+    Set { message: &msg }.execute(&storage).await?;
 
     // 4. Verify update
     let msg = protocol::array(vec![
         protocol::bulk_string("GET"),
         protocol::bulk_string("cache:config:timeout"),
     ]);
-    let result = Get { message: &msg }.execute(&storage)?;
+    let result = Get { message: &msg }.execute(&storage).await?;
     assert_eq!(result[0].as_string()?, "60000");
 
     // 5. Test nonexistent
@@ -421,13 +421,13 @@ fn e2e_mixed_operations() -> Result<(), Box<dyn Error>> {
         protocol::bulk_string("GET"),
         protocol::bulk_string("cache:nonexistent"),
     ]);
-    let result = Get { message: &msg }.execute(&storage)?;
+    let result = Get { message: &msg }.execute(&storage).await?;
     assert_eq!(result[0].as_string()?, "");
     Ok(())
 }
 
-#[test]
-fn e2e_session_simulation() -> Result<(), Box<dyn Error>> {
+#[tokio::test]
+async fn e2e_session_simulation() -> Result<(), Box<dyn Error>> {
     let storage = create_test_storage();
 
     // Simulate a user session cache
@@ -443,25 +443,25 @@ fn e2e_session_simulation() -> Result<(), Box<dyn Error>> {
         protocol::bulk_string("px"),
         protocol::bulk_string(&expiry_ms.to_string()),
     ]);
-    Set { message: &msg }.execute(&storage)?;
+    Set { message: &msg }.execute(&storage).await?;
 
     // Retrieve session
     let msg = protocol::array(vec![
         protocol::bulk_string("GET"),
         protocol::bulk_string(session_id),
     ]);
-    let result = Get { message: &msg }.execute(&storage)?;
+    let result = Get { message: &msg }.execute(&storage).await?;
     assert_eq!(result[0].as_string()?, user_id);
 
     // Session should still be valid after 100ms
-    thread::sleep(Duration::from_millis(100));
-    let result = Get { message: &msg }.execute(&storage)?;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let result = Get { message: &msg }.execute(&storage).await?;
     assert_eq!(result[0].as_string()?, user_id);
     Ok(())
 }
 
-#[test]
-fn e2e_user_profile_caching() -> Result<(), Box<dyn Error>> {
+#[tokio::test]
+async fn e2e_user_profile_caching() -> Result<(), Box<dyn Error>> {
     let storage = create_test_storage();
 
     // Simulate caching user profile
@@ -476,20 +476,20 @@ fn e2e_user_profile_caching() -> Result<(), Box<dyn Error>> {
         protocol::bulk_string("px"),
         protocol::bulk_string("600000"), // 10 minutes
     ]);
-    Set { message: &msg }.execute(&storage)?;
+    Set { message: &msg }.execute(&storage).await?;
 
     // Retrieve user profile
     let msg = protocol::array(vec![
         protocol::bulk_string("GET"),
         protocol::bulk_string(&format!("profile:{}", user_id)),
     ]);
-    let result = Get { message: &msg }.execute(&storage)?;
+    let result = Get { message: &msg }.execute(&storage).await?;
     assert_eq!(result[0].as_string()?, profile_json);
     Ok(())
 }
 
-#[test]
-fn e2e_rate_limiting_with_expiration() -> Result<(), Box<dyn Error>> {
+#[tokio::test]
+async fn e2e_rate_limiting_with_expiration() -> Result<(), Box<dyn Error>> {
     let storage = create_test_storage();
 
     // Simulate rate limiter that expires after 60 seconds
@@ -505,7 +505,7 @@ fn e2e_rate_limiting_with_expiration() -> Result<(), Box<dyn Error>> {
             protocol::bulk_string("px"),
             protocol::bulk_string("60000"),
         ]);
-        Set { message: &msg }.execute(&storage)?;
+        Set { message: &msg }.execute(&storage).await?;
     }
 
     // Verify final count
@@ -513,7 +513,7 @@ fn e2e_rate_limiting_with_expiration() -> Result<(), Box<dyn Error>> {
         protocol::bulk_string("GET"),
         protocol::bulk_string(&rate_limit_key),
     ]);
-    let result = Get { message: &msg }.execute(&storage)?;
+    let result = Get { message: &msg }.execute(&storage).await?;
     assert_eq!(result[0].as_string()?, "3");
     Ok(())
 }
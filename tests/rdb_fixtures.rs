@@ -290,9 +290,9 @@ fn fixture_string_keys() {
     let data = fs::read(fixtures_dir().join("string_keys.rdb")).unwrap();
     let storage = from_rdb(Cursor::new(&data)).unwrap();
     assert_eq!(storage.data.len(), 3);
-    assert_eq!(storage.data.get("name").unwrap().value, b"Redis");
-    assert_eq!(storage.data.get("version").unwrap().value, b"7.0.0");
-    assert_eq!(storage.data.get("lang").unwrap().value, b"C");
+    assert_eq!(storage.data.get("name".as_bytes()).unwrap().value.as_bytes().unwrap(), b"Redis");
+    assert_eq!(storage.data.get("version".as_bytes()).unwrap().value.as_bytes().unwrap(), b"7.0.0");
+    assert_eq!(storage.data.get("lang".as_bytes()).unwrap().value.as_bytes().unwrap(), b"C");
 }
 
 #[test]
@@ -303,14 +303,14 @@ fn fixture_with_expiry() {
     let data = fs::read(fixtures_dir().join("with_expiry.rdb")).unwrap();
     let storage = from_rdb(Cursor::new(&data)).unwrap();
     assert_eq!(storage.data.len(), 3);
-    assert_eq!(storage.data.get("session:abc").unwrap().value, b"user123");
-    assert_eq!(storage.data.get("session:def").unwrap().value, b"user456");
-    assert_eq!(storage.data.get("permanent").unwrap().value, b"stays");
+    assert_eq!(storage.data.get("session:abc".as_bytes()).unwrap().value.as_bytes().unwrap(), b"user123");
+    assert_eq!(storage.data.get("session:def".as_bytes()).unwrap().value.as_bytes().unwrap(), b"user456");
+    assert_eq!(storage.data.get("permanent".as_bytes()).unwrap().value.as_bytes().unwrap(), b"stays");
 
     // Verify expiry is set on session keys but not on permanent
-    assert!(storage.data.get("session:abc").unwrap().expires_at_ms().is_some());
-    assert!(storage.data.get("session:def").unwrap().expires_at_ms().is_some());
-    assert!(storage.data.get("permanent").unwrap().expires_at_ms().is_none());
+    assert!(storage.data.get("session:abc".as_bytes()).unwrap().expires_at_ms().is_some());
+    assert!(storage.data.get("session:def".as_bytes()).unwrap().expires_at_ms().is_some());
+    assert!(storage.data.get("permanent".as_bytes()).unwrap().expires_at_ms().is_none());
 }
 
 #[test]
@@ -321,10 +321,10 @@ fn fixture_integer_encoded() {
     let data = fs::read(fixtures_dir().join("integer_encoded.rdb")).unwrap();
     let storage = from_rdb(Cursor::new(&data)).unwrap();
     assert_eq!(storage.data.len(), 4);
-    assert_eq!(storage.data.get("small_num").unwrap().value, b"42");
-    assert_eq!(storage.data.get("neg_num").unwrap().value, b"-5");
-    assert_eq!(storage.data.get("medium_num").unwrap().value, b"10000");
-    assert_eq!(storage.data.get("large_num").unwrap().value, b"1000000");
+    assert_eq!(storage.data.get("small_num".as_bytes()).unwrap().value.as_bytes().unwrap(), b"42");
+    assert_eq!(storage.data.get("neg_num".as_bytes()).unwrap().value.as_bytes().unwrap(), b"-5");
+    assert_eq!(storage.data.get("medium_num".as_bytes()).unwrap().value.as_bytes().unwrap(), b"10000");
+    assert_eq!(storage.data.get("large_num".as_bytes()).unwrap().value.as_bytes().unwrap(), b"1000000");
 }
 
 #[test]
@@ -335,7 +335,7 @@ fn fixture_aux_and_resize() {
     let data = fs::read(fixtures_dir().join("aux_and_resize.rdb")).unwrap();
     let storage = from_rdb(Cursor::new(&data)).unwrap();
     assert_eq!(storage.data.len(), 1);
-    assert_eq!(storage.data.get("greeting").unwrap().value, b"hello");
+    assert_eq!(storage.data.get("greeting".as_bytes()).unwrap().value.as_bytes().unwrap(), b"hello");
 }
 
 #[test]
@@ -347,9 +347,9 @@ fn fixture_multiple_databases() {
     let storage = from_rdb(Cursor::new(&data)).unwrap();
     // All keys from all databases loaded into our single storage
     assert_eq!(storage.data.len(), 3);
-    assert_eq!(storage.data.get("db0:key1").unwrap().value, b"val1");
-    assert_eq!(storage.data.get("db0:key2").unwrap().value, b"val2");
-    assert_eq!(storage.data.get("db1:key1").unwrap().value, b"db1val");
+    assert_eq!(storage.data.get("db0:key1".as_bytes()).unwrap().value.as_bytes().unwrap(), b"val1");
+    assert_eq!(storage.data.get("db0:key2".as_bytes()).unwrap().value.as_bytes().unwrap(), b"val2");
+    assert_eq!(storage.data.get("db1:key1".as_bytes()).unwrap().value.as_bytes().unwrap(), b"db1val");
 }
 
 #[test]
@@ -361,13 +361,13 @@ fn fixture_mixed_types() {
     let storage = from_rdb(Cursor::new(&data)).unwrap();
     // Only string keys should be loaded
     assert_eq!(storage.data.len(), 2);
-    assert_eq!(storage.data.get("string_key").unwrap().value, b"string_val");
+    assert_eq!(storage.data.get("string_key".as_bytes()).unwrap().value.as_bytes().unwrap(), b"string_val");
     assert_eq!(
-        storage.data.get("another_string").unwrap().value,
+        storage.data.get("another_string".as_bytes()).unwrap().value.as_bytes().unwrap(),
         b"another_val"
     );
-    assert!(storage.data.get("hash_key").is_none());
-    assert!(storage.data.get("set_key").is_none());
+    assert!(storage.data.get("hash_key".as_bytes()).is_none());
+    assert!(storage.data.get("set_key".as_bytes()).is_none());
 }
 
 #[test]
@@ -378,30 +378,30 @@ fn fixture_with_expired_keys() {
     let data = fs::read(fixtures_dir().join("with_expired_keys.rdb")).unwrap();
     let storage = from_rdb(Cursor::new(&data)).unwrap();
     // Expired keys should be filtered out
-    assert!(storage.data.get("expired1").is_none());
-    assert!(storage.data.get("expired2").is_none());
-    assert_eq!(storage.data.get("valid").unwrap().value, b"fresh_data");
+    assert!(storage.data.get("expired1".as_bytes()).is_none());
+    assert!(storage.data.get("expired2".as_bytes()).is_none());
+    assert_eq!(storage.data.get("valid".as_bytes()).unwrap().value.as_bytes().unwrap(), b"fresh_data");
 }
 
 #[test]
 fn rdb_round_trip_via_storage_api() {
     let mut storage = Storage::new(HashMap::new());
     storage
-        .set("user:1", b"alice".to_vec(), None)
+        .set("user:1".as_bytes(), b"alice".to_vec(), None)
         .unwrap();
     storage
-        .set("user:2", b"bob".to_vec(), Some(3_600_000))
+        .set("user:2".as_bytes(), b"bob".to_vec(), Some(3_600_000))
         .unwrap();
     storage
-        .set("counter", b"42".to_vec(), None)
+        .set("counter".as_bytes(), b"42".to_vec(), None)
         .unwrap();
 
     let rdb_bytes = storage.to_rdb().unwrap();
     let loaded = Storage::from_rdb(&rdb_bytes).unwrap();
 
-    assert_eq!(loaded.to_pairs().get("user:1"), Some(&b"alice".to_vec()));
-    assert_eq!(loaded.to_pairs().get("user:2"), Some(&b"bob".to_vec()));
-    assert_eq!(loaded.to_pairs().get("counter"), Some(&b"42".to_vec()));
+    assert_eq!(loaded.to_pairs().get("user:1".as_bytes()), Some(&b"alice".to_vec()));
+    assert_eq!(loaded.to_pairs().get("user:2".as_bytes()), Some(&b"bob".to_vec()));
+    assert_eq!(loaded.to_pairs().get("counter".as_bytes()), Some(&b"42".to_vec()));
 }
 
 #[test]
@@ -409,26 +409,26 @@ fn rdb_round_trip_binary_values() {
     let mut storage = Storage::new(HashMap::new());
     let binary = vec![0u8, 1, 2, 127, 128, 254, 255];
     storage
-        .set("binary", binary.clone(), None)
+        .set("binary".as_bytes(), binary.clone(), None)
         .unwrap();
 
     let rdb_bytes = storage.to_rdb().unwrap();
     let loaded = Storage::from_rdb(&rdb_bytes).unwrap();
 
-    assert_eq!(loaded.to_pairs().get("binary"), Some(&binary));
+    assert_eq!(loaded.to_pairs().get("binary".as_bytes()), Some(&binary));
 }
 
 #[test]
 fn rdb_round_trip_empty_values() {
     let mut storage = Storage::new(HashMap::new());
-    storage.set("empty", b"".to_vec(), None).unwrap();
+    storage.set("empty".as_bytes(), b"".to_vec(), None).unwrap();
     storage
-        .set("notempty", b"x".to_vec(), None)
+        .set("notempty".as_bytes(), b"x".to_vec(), None)
         .unwrap();
 
     let rdb_bytes = storage.to_rdb().unwrap();
     let loaded = Storage::from_rdb(&rdb_bytes).unwrap();
 
-    assert_eq!(loaded.to_pairs().get("empty"), Some(&b"".to_vec()));
-    assert_eq!(loaded.to_pairs().get("notempty"), Some(&b"x".to_vec()));
+    assert_eq!(loaded.to_pairs().get("empty".as_bytes()), Some(&b"".to_vec()));
+    assert_eq!(loaded.to_pairs().get("notempty".as_bytes()), Some(&b"x".to_vec()));
 }
@@ -0,0 +1,176 @@
+/// A test-only replication harness exercising the master side of the
+/// replication handshake end to end.
+///
+/// `PSync::on_connection` registers a replica through a
+/// `secure_transport::WriteHandle`, which wraps a concrete
+/// `std::net::TcpStream` rather than a generic stream, so this harness pairs
+/// a loopback `TcpListener`/`TcpStream` and hands the master side over as a
+/// plain (unencrypted) handle instead of using a literal in-memory duplex
+/// pipe - it still runs entirely in-process, with no server accept loop or
+/// dispatch thread involved. `MockReplica` holds the replica
+/// side of that pair together with every message it has parsed off the
+/// wire so far; `connect_replica` drives the master-side `PING` ->
+/// `REPLCONF listening-port` -> `REPLCONF capa` -> `PSYNC ? -1` exchange the
+/// same way `connection::handle_connection`'s dispatch loop would, one
+/// command at a time, and returns once the replica has received
+/// `FULLRESYNC` and the RDB snapshot.
+
+use codecrafters_redis::commands::*;
+use codecrafters_redis::protocol::{self, DataType};
+use codecrafters_redis::secure_transport;
+use codecrafters_redis::server_state::ServerState;
+use codecrafters_redis::storage::{Storage, StoredValue};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+fn test_storage() -> Arc<Mutex<Storage>> {
+    let data: HashMap<Vec<u8>, StoredValue> = HashMap::new();
+    Arc::new(Mutex::new(Storage::new(data)))
+}
+
+/// Writes `bytes` to `socket` split into pieces of at most `chunk_size`,
+/// pausing briefly between pieces so the reader observes them as separate
+/// reads instead of one coalesced one - used to verify the receiving side
+/// reassembles a frame split across reads.
+fn write_in_chunks(mut socket: &TcpStream, bytes: &[u8], chunk_size: usize) {
+    for chunk in bytes.chunks(chunk_size.max(1)) {
+        socket.write_all(chunk).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
+
+/// The replica side of a loopback TCP pair, together with every fully
+/// parsed message read off it so far.
+struct MockReplica {
+    socket: TcpStream,
+    read_buffer: Vec<u8>,
+    received: Vec<DataType>,
+}
+
+impl MockReplica {
+    /// Blocks for at least one more read, parses as many complete frames as
+    /// are now available, appends them to `received`, and returns just the
+    /// newly parsed ones.
+    fn read_available(&mut self) -> Vec<DataType> {
+        let mut chunk = [0u8; 4096];
+        let read_bytes = self.socket.read(&mut chunk).unwrap();
+        self.read_buffer.extend_from_slice(&chunk[0..read_bytes]);
+        let (messages, consumed) = protocol::read_messages_from_bytes(&self.read_buffer).unwrap();
+        self.read_buffer.drain(0..consumed);
+        self.received.extend(messages.clone());
+        messages
+    }
+}
+
+/// Runs the full handshake against `server_state`/`storage`, writing each
+/// master reply to the wire in pieces of at most `reply_chunk_size` bytes.
+async fn connect_replica(server_state: &Arc<ServerState>, storage: &Arc<Mutex<Storage>>, reply_chunk_size: usize) -> MockReplica {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let replica_socket = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (master_socket, _) = listener.accept().unwrap();
+    let mut replica = MockReplica { socket: replica_socket, read_buffer: Vec::new(), received: Vec::new() };
+
+    let ping = Ping { message: &protocol::array(vec![protocol::bulk_string("PING")]) };
+    let reply = ping.execute(storage).await.unwrap();
+    write_in_chunks(&master_socket, &reply[0].serialize(), reply_chunk_size);
+    assert_eq!(replica.read_available()[0].as_string().unwrap(), "PONG");
+
+    let listening_port_message = protocol::array(vec![
+        protocol::bulk_string("REPLCONF"),
+        protocol::bulk_string("listening-port"),
+        protocol::bulk_string("6380"),
+    ]);
+    let replconf = ReplConf { message: &listening_port_message, server_state, replica_offset: 0, peer_address: None };
+    let reply = replconf.execute(storage).await.unwrap();
+    write_in_chunks(&master_socket, &reply[0].serialize(), reply_chunk_size);
+    assert_eq!(replica.read_available()[0].as_string().unwrap(), "OK");
+
+    let capa_message = protocol::array(vec![
+        protocol::bulk_string("REPLCONF"),
+        protocol::bulk_string("capa"),
+        protocol::bulk_string("psync2"),
+    ]);
+    let replconf = ReplConf { message: &capa_message, server_state, replica_offset: 0, peer_address: None };
+    let reply = replconf.execute(storage).await.unwrap();
+    write_in_chunks(&master_socket, &reply[0].serialize(), reply_chunk_size);
+    assert_eq!(replica.read_available()[0].as_string().unwrap(), "OK");
+
+    let psync_message = protocol::array(vec![
+        protocol::bulk_string("PSYNC"),
+        protocol::bulk_string("?"),
+        protocol::bulk_string("-1"),
+    ]);
+    let psync = PSync { message: &psync_message, server_state, peer_address: None };
+    psync.on_connection(&secure_transport::plain_handle(master_socket.try_clone().unwrap()), server_state).unwrap();
+    let reply = psync.execute(storage).await.unwrap();
+    for message in &reply {
+        write_in_chunks(&master_socket, &message.serialize(), reply_chunk_size);
+    }
+
+    let mut handshake_reply = replica.read_available();
+    while handshake_reply.len() < reply.len() {
+        handshake_reply.extend(replica.read_available());
+    }
+    assert!(handshake_reply[0].as_string().unwrap().starts_with("FULLRESYNC"));
+    assert!(matches!(handshake_reply[1], DataType::Rdb { .. }));
+
+    replica
+}
+
+#[tokio::test]
+async fn e2e_replica_receives_rdb_then_stream() {
+    let server_state = Arc::new(ServerState::new(None, 6379));
+    let storage = test_storage();
+    // Deliver every handshake reply (including the RDB payload) five bytes
+    // at a time, forcing the replica's reader to reassemble frames split
+    // across many short reads.
+    let mut replica = connect_replica(&server_state, &storage, 5).await;
+
+    let set_message = protocol::array(vec![
+        protocol::bulk_string("SET"),
+        protocol::bulk_string("key"),
+        protocol::bulk_string("value"),
+    ]);
+    let set_cmd = Set { message: &set_message };
+    set_cmd.execute(&storage).await.unwrap();
+    let command_bytes = set_cmd.serialize();
+    server_state.record_propagated_bytes(&command_bytes).unwrap();
+    server_state.broadcast_to_replicas(&command_bytes).unwrap();
+
+    let propagated = replica.read_available();
+    assert_eq!(propagated.len(), 1);
+    assert_eq!(propagated[0].as_vec().unwrap(), vec!["SET", "key", "value"]);
+}
+
+#[tokio::test]
+async fn e2e_replication_propagates_set() {
+    let server_state = Arc::new(ServerState::new(None, 6379));
+    let storage = test_storage();
+    let mut replica = connect_replica(&server_state, &storage, usize::MAX).await;
+
+    for (key, value) in [("a", "1"), ("b", "2")] {
+        let set_message = protocol::array(vec![
+            protocol::bulk_string("SET"),
+            protocol::bulk_string(key),
+            protocol::bulk_string(value),
+        ]);
+        let set_cmd = Set { message: &set_message };
+        set_cmd.execute(&storage).await.unwrap();
+        let command_bytes = set_cmd.serialize();
+        server_state.record_propagated_bytes(&command_bytes).unwrap();
+        server_state.broadcast_to_replicas(&command_bytes).unwrap();
+    }
+
+    let mut propagated = Vec::new();
+    while propagated.len() < 2 {
+        propagated.extend(replica.read_available());
+    }
+    assert_eq!(propagated[0].as_vec().unwrap(), vec!["SET", "a", "1"]);
+    assert_eq!(propagated[1].as_vec().unwrap(), vec!["SET", "b", "2"]);
+    // The handshake's PONG/OK/OK/FULLRESYNC/RDB plus both propagated SETs.
+    assert_eq!(replica.received.len(), 7);
+}